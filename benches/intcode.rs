@@ -0,0 +1,329 @@
+//! Compares the interpreter as it was before the decoded-instruction cache
+//! with the current one, with and without the cache.
+//!
+//! Only loops are expected to gain: the cache starts at the first backward
+//! jump, so the straight-line day 2 program runs as fast as before, give or
+//! take noise.
+//!
+//! Run with `cargo bench`.
+
+use aoc_2019::intcode::asm::assemble;
+use aoc_2019::intcode::Process;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ROUNDS: usize = 5;
+
+/// The execution path before the cache, kept here to measure against: it
+/// decodes every instruction it executes, and sizing one allocated its list
+/// of operands.
+mod before {
+    use aoc_2019::intcode::{Instruction, IntcodeError, Parameter, ParameterMode, MAX_ADDRESS};
+    use std::collections::VecDeque;
+
+    #[derive(Clone)]
+    pub struct Process {
+        pub code: Vec<i64>,
+        ip: usize,
+        relative_base: i64,
+        pub input: VecDeque<i64>,
+        pub output: VecDeque<i64>,
+    }
+
+    fn size(instruction: &Instruction) -> usize {
+        instruction.params().to_vec().len() + 1
+    }
+
+    impl Process {
+        pub fn new(code: Vec<i64>) -> Self {
+            Self {
+                code,
+                ip: 0,
+                relative_base: 0,
+                input: VecDeque::new(),
+                output: VecDeque::new(),
+            }
+        }
+
+        pub fn read(&self, addr: usize) -> i64 {
+            self.code.get(addr).copied().unwrap_or(0)
+        }
+
+        pub fn write(&mut self, addr: usize, value: i64) {
+            if addr >= self.code.len() {
+                self.code.resize(addr + 1, 0);
+            }
+            self.code[addr] = value;
+        }
+
+        fn address(&self, addr: i64) -> Result<usize, IntcodeError> {
+            if (0..MAX_ADDRESS).contains(&addr) {
+                Ok(addr as usize)
+            } else {
+                Err(IntcodeError::InvalidAddress {
+                    ip: self.ip,
+                    opcode: self.read(self.ip),
+                    addr,
+                })
+            }
+        }
+
+        fn param_addr(&self, param: Parameter) -> Result<usize, IntcodeError> {
+            match param.mode {
+                ParameterMode::Position => self.address(param.value),
+                ParameterMode::Immediate => Err(IntcodeError::ImmediateWrite {
+                    ip: self.ip,
+                    opcode: self.read(self.ip),
+                }),
+                ParameterMode::Relative => self.address(self.relative_base + param.value),
+            }
+        }
+
+        fn load(&self, param: Parameter) -> Result<i64, IntcodeError> {
+            match param.mode {
+                ParameterMode::Immediate => Ok(param.value),
+                _ => Ok(self.read(self.param_addr(param)?)),
+            }
+        }
+
+        fn store(&mut self, param: Parameter, value: i64) -> Result<(), IntcodeError> {
+            let addr = self.param_addr(param)?;
+            self.write(addr, value);
+            Ok(())
+        }
+
+        fn exec(&mut self) -> Result<Option<i64>, IntcodeError> {
+            use Instruction::*;
+            let ip = self.ip;
+            let instruction = Instruction::decode(&self.code, ip)?;
+            let mut output = None;
+            let mut next = ip + size(&instruction);
+            match instruction {
+                Add(a, b, dest) => self.store(dest, self.load(a)? + self.load(b)?)?,
+                Mul(a, b, dest) => self.store(dest, self.load(a)? * self.load(b)?)?,
+                LessThan(a, b, dest) => {
+                    let value = if self.load(a)? < self.load(b)? { 1 } else { 0 };
+                    self.store(dest, value)?
+                }
+                Equals(a, b, dest) => {
+                    let value = if self.load(a)? == self.load(b)? { 1 } else { 0 };
+                    self.store(dest, value)?
+                }
+                In(dest) => match self.input.pop_front() {
+                    Some(input) => self.store(dest, input)?,
+                    None => {
+                        return Err(IntcodeError::NoInput {
+                            ip,
+                            opcode: self.read(ip),
+                        })
+                    }
+                },
+                Out(a) => output = Some(self.load(a)?),
+                JumpIfTrue(cond, target) | JumpIfFalse(cond, target) => {
+                    let cond = self.load(cond)?;
+                    if (cond != 0) == matches!(instruction, JumpIfTrue(..)) {
+                        let target = self.load(target)?;
+                        next = self.address(target)?;
+                    }
+                }
+                AdjustRelativeBase(a) => self.relative_base += self.load(a)?,
+                Halt => next = ip,
+            }
+            self.ip = next;
+            Ok(output)
+        }
+
+        pub fn execute(&mut self) -> Result<(), IntcodeError> {
+            while self.read(self.ip) != 99 {
+                if let Some(output) = self.exec()? {
+                    self.output.push_back(output);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// What the workloads need from an interpreter.
+trait Interpreter: Clone {
+    fn load(code: Vec<i64>) -> Self;
+    fn write(&mut self, addr: usize, value: i64);
+    fn read(&self, addr: usize) -> i64;
+    fn input(&mut self, value: i64);
+    fn output(&mut self) -> Option<i64>;
+    fn execute(&mut self);
+}
+
+impl Interpreter for before::Process {
+    fn load(code: Vec<i64>) -> Self {
+        Self::new(code)
+    }
+
+    fn write(&mut self, addr: usize, value: i64) {
+        before::Process::write(self, addr, value)
+    }
+
+    fn read(&self, addr: usize) -> i64 {
+        before::Process::read(self, addr)
+    }
+
+    fn input(&mut self, value: i64) {
+        self.input.push_back(value)
+    }
+
+    fn output(&mut self) -> Option<i64> {
+        self.output.pop_front()
+    }
+
+    fn execute(&mut self) {
+        before::Process::execute(self).unwrap()
+    }
+}
+
+/// The current interpreter with the decode cache on or off.
+#[derive(Clone)]
+struct Current<const CACHED: bool>(Process);
+
+impl<const CACHED: bool> Interpreter for Current<CACHED> {
+    fn load(code: Vec<i64>) -> Self {
+        let mut process = Process::with_code(code, Default::default());
+        process.set_decode_cache(CACHED);
+        Self(process)
+    }
+
+    fn write(&mut self, addr: usize, value: i64) {
+        self.0.write(addr, value)
+    }
+
+    fn read(&self, addr: usize) -> i64 {
+        self.0.read(addr)
+    }
+
+    fn input(&mut self, value: i64) {
+        self.0.input(value)
+    }
+
+    fn output(&mut self) -> Option<i64> {
+        self.0.output()
+    }
+
+    fn execute(&mut self) {
+        self.0.execute().unwrap()
+    }
+}
+
+const COUNTDOWN: &str = "
+            in [n]
+    loop:   add [n], #-1, [n]
+            mul [n], #3, [t]
+            lt [t], [n], [t]
+            jnz [n], loop
+            out [t]
+            halt
+    n:      .data 0
+    t:      .data 0
+";
+
+fn parse(data: &str) -> Vec<i64> {
+    data.trim().split(',').map(|n| n.parse().unwrap()).collect()
+}
+
+fn countdown<I: Interpreter>() -> i64 {
+    let mut process = I::load(assemble(COUNTDOWN).unwrap());
+    process.input(1_000_000);
+    process.execute();
+    process.output().unwrap()
+}
+
+fn day2_search<I: Interpreter>() -> i64 {
+    let base = I::load(parse(include_str!("../input/2019/day2.txt")));
+    let mut found = 0;
+    for noun in 0..=99 {
+        for verb in 0..=99 {
+            let mut process = base.clone();
+            process.write(1, noun);
+            process.write(2, verb);
+            process.execute();
+            if process.read(0) == 19_690_720 {
+                found = noun * 100 + verb;
+            }
+        }
+    }
+    found
+}
+
+fn day5_diagnostics<I: Interpreter>() -> i64 {
+    let base = I::load(parse(include_str!("../input/2019/day5.txt")));
+    let mut sum = 0;
+    for _ in 0..1000 {
+        let mut process = base.clone();
+        process.input(5);
+        process.execute();
+        sum += process.output().unwrap();
+    }
+    sum
+}
+
+fn best_of(workload: fn() -> i64) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(workload());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+/// A workload on the interpreter before the cache, and on the current one
+/// without and with it.
+type Workload = [fn() -> i64; 3];
+
+fn main() {
+    let workloads: [(&str, Workload); 3] = [
+        (
+            "countdown loop",
+            [
+                countdown::<before::Process>,
+                countdown::<Current<false>>,
+                countdown::<Current<true>>,
+            ],
+        ),
+        (
+            "day2 noun/verb search",
+            [
+                day2_search::<before::Process>,
+                day2_search::<Current<false>>,
+                day2_search::<Current<true>>,
+            ],
+        ),
+        (
+            "day5 diagnostics x1000",
+            [
+                day5_diagnostics::<before::Process>,
+                day5_diagnostics::<Current<false>>,
+                day5_diagnostics::<Current<true>>,
+            ],
+        ),
+    ];
+    println!(
+        "{:<24}{:>12}{:>12}{:>12}{:>10}",
+        "workload", "before", "uncached", "cached", "speedup"
+    );
+    for (name, [before, uncached, cached]) in workloads.iter() {
+        let answer = before();
+        assert_eq!(uncached(), answer, "{} disagrees", name);
+        assert_eq!(cached(), answer, "{} disagrees", name);
+        let before = best_of(*before);
+        let uncached = best_of(*uncached);
+        let cached = best_of(*cached);
+        println!(
+            "{:<24}{:>12.2?}{:>12.2?}{:>12.2?}{:>9.2}x",
+            name,
+            before,
+            uncached,
+            cached,
+            before.as_secs_f64() / cached.as_secs_f64()
+        );
+    }
+}
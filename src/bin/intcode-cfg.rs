@@ -0,0 +1,66 @@
+//! Prints the static control-flow graph of an Intcode program as Graphviz DOT.
+//!
+//! Usage: `intcode-cfg <program file> [ADDR=VALUE]... | dot -Tsvg > cfg.svg`
+//!
+//! Analysis stops at words a self-modifying program patches before running
+//! them. `ADDR=VALUE` arguments apply such patches up front, e.g. `6=1101`
+//! for day 5 given input 1.
+
+use aoc_2019::intcode::cfg::control_flow_graph;
+use aoc_2019::intcode::{BufferIo, Process, MAX_ADDRESS};
+use std::io;
+
+/// Parses `ADDR=VALUE` with `ADDR` below `MAX_ADDRESS`.
+fn parse_patch(arg: &str) -> Option<(usize, i64)> {
+    let mut parts = arg.splitn(2, '=');
+    let addr = parts.next()?.parse().ok()?;
+    let value = parts.next()?.parse().ok()?;
+    if addr >= MAX_ADDRESS as usize {
+        return None;
+    }
+    Some((addr, value))
+}
+
+fn parse_program(text: &str) -> Result<Vec<i64>, String> {
+    text.trim()
+        .split(',')
+        .map(|n| {
+            n.trim()
+                .parse()
+                .map_err(|_| format!("invalid program word `{}`", n.trim()))
+        })
+        .collect()
+}
+
+fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = match args.next() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: intcode-cfg <program file> [ADDR=VALUE]...");
+            std::process::exit(2);
+        }
+    };
+    let code = match parse_program(&std::fs::read_to_string(path)?) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let mut process = Process::with_code(code, BufferIo::default());
+    for arg in args {
+        match parse_patch(&arg) {
+            Some((addr, value)) => process.write(addr, value),
+            None => {
+                eprintln!(
+                    "invalid patch `{}`, expected ADDR=VALUE with ADDR below {}",
+                    arg, MAX_ADDRESS
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+    print!("{}", control_flow_graph(&process.code).to_dot());
+    Ok(())
+}
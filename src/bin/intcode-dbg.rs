@@ -0,0 +1,221 @@
+//! Interactive debugger for Intcode programs.
+//!
+//! Usage: `intcode-dbg <program file>`, then `help` at the prompt.
+
+use aoc_2019::intcode::{BufferIo, Instruction, IntcodeError, Process, MAX_ADDRESS};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+step [N]           execute N instructions (default 1)
+continue           run until a breakpoint, halt or missing input
+break ADDR         break when ip reaches ADDR
+break op OPCODE    break before any instruction with OPCODE
+delete ADDR        remove an address breakpoint
+delete op OPCODE   remove an opcode breakpoint
+breakpoints        list breakpoints
+print ADDR [N]     print N words of memory starting at ADDR
+set ADDR VALUE     write VALUE to ADDR
+input V...         queue input values
+output             show and drain pending output
+inst               show ip, relative base and the current instruction
+quit               exit";
+
+struct Debugger {
+    process: Process,
+    addr_breaks: BTreeSet<usize>,
+    op_breaks: BTreeSet<i64>,
+}
+
+fn parse<T: std::str::FromStr>(arg: Option<&str>, what: &str) -> Result<T, String> {
+    let arg = arg.ok_or_else(|| format!("missing {}", what))?;
+    arg.parse()
+        .map_err(|_| format!("invalid {} `{}`", what, arg))
+}
+
+/// Parses an address the process can hold, below `MAX_ADDRESS`.
+fn parse_addr(arg: Option<&str>) -> Result<usize, String> {
+    let addr: usize = parse(arg, "address")?;
+    if addr >= MAX_ADDRESS as usize {
+        return Err(format!("address {} out of range", addr));
+    }
+    Ok(addr)
+}
+
+fn parse_program(text: &str) -> Result<Vec<i64>, String> {
+    text.trim()
+        .split(',')
+        .map(|n| parse(Some(n.trim()), "program word"))
+        .collect()
+}
+
+impl Debugger {
+    fn current(&self) -> String {
+        let instruction = match Instruction::decode(&self.process.code, self.process.ip) {
+            Ok(instruction) => instruction.to_string(),
+            Err(err) => format!("<{}>", err),
+        };
+        format!(
+            "ip={} rb={}  {}",
+            self.process.ip, self.process.relative_base, instruction
+        )
+    }
+
+    fn is_breakpoint(&self) -> bool {
+        let ip = self.process.ip;
+        self.addr_breaks.contains(&ip) || self.op_breaks.contains(&(self.process.read(ip) % 100))
+    }
+
+    /// Steps once, reporting why execution can't go on if it can't.
+    fn step(&mut self) -> Result<(), String> {
+        if self.process.is_finished() {
+            return Err("halted".to_owned());
+        }
+        self.process.step().map_err(|err| match err {
+            IntcodeError::NoInput { .. } => "waiting for input".to_owned(),
+            err => format!("error {}", err),
+        })
+    }
+
+    fn step_n(&mut self, n: usize) {
+        for _ in 0..n {
+            if let Err(reason) = self.step() {
+                println!("{}", reason);
+                break;
+            }
+        }
+        println!("{}", self.current());
+    }
+
+    fn cont(&mut self) {
+        let mut steps = 0;
+        loop {
+            if let Err(reason) = self.step() {
+                println!("{} after {} steps", reason, steps);
+                break;
+            }
+            steps += 1;
+            if self.is_breakpoint() {
+                println!("breakpoint after {} steps", steps);
+                break;
+            }
+        }
+        println!("{}", self.current());
+    }
+
+    fn breakpoint(&mut self, args: &[&str], add: bool) -> Result<(), String> {
+        let changed = if args.first() == Some(&"op") {
+            let opcode = parse(args.get(1).copied(), "opcode")?;
+            if add {
+                self.op_breaks.insert(opcode)
+            } else {
+                self.op_breaks.remove(&opcode)
+            }
+        } else {
+            let addr = parse(args.first().copied(), "address")?;
+            if add {
+                self.addr_breaks.insert(addr)
+            } else {
+                self.addr_breaks.remove(&addr)
+            }
+        };
+        if !changed {
+            println!("nothing changed");
+        }
+        Ok(())
+    }
+
+    /// Runs one command line; returns `Ok(false)` when the user quits.
+    fn command(&mut self, line: &str) -> Result<bool, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (cmd, args) = match words.split_first() {
+            Some((cmd, args)) => (*cmd, args),
+            None => return Ok(true),
+        };
+        match cmd {
+            "s" | "step" => {
+                let n = args.first().map_or(Ok(1), |n| parse(Some(n), "count"))?;
+                self.step_n(n)
+            }
+            "c" | "continue" => self.cont(),
+            "b" | "break" => self.breakpoint(args, true)?,
+            "d" | "delete" => self.breakpoint(args, false)?,
+            "bl" | "breakpoints" => {
+                println!("addresses: {:?}", self.addr_breaks);
+                println!("opcodes: {:?}", self.op_breaks);
+            }
+            "p" | "print" => {
+                let addr = parse_addr(args.first().copied())?;
+                let n: usize = args.get(1).map_or(Ok(1), |n| parse(Some(n), "count"))?;
+                let end = addr
+                    .checked_add(n)
+                    .filter(|&end| end <= MAX_ADDRESS as usize)
+                    .ok_or_else(|| format!("{} words from {} out of range", n, addr))?;
+                for row in (addr..end).step_by(8) {
+                    let words: Vec<String> = (row..end.min(row + 8))
+                        .map(|a| self.process.read(a).to_string())
+                        .collect();
+                    println!("{:04}: {}", row, words.join(" "));
+                }
+            }
+            "set" => {
+                let addr = parse_addr(args.first().copied())?;
+                let value: i64 = parse(args.get(1).copied(), "value")?;
+                self.process.write(addr, value);
+            }
+            "i" | "input" => {
+                for arg in args {
+                    let value = parse(Some(arg), "value")?;
+                    self.process.input(value);
+                }
+            }
+            "o" | "output" => {
+                let output: Vec<String> =
+                    self.process.output_iter().map(|n| n.to_string()).collect();
+                println!("{}", output.join(","));
+            }
+            "x" | "inst" => println!("{}", self.current()),
+            "h" | "help" => println!("{}", HELP),
+            "q" | "quit" => return Ok(false),
+            _ => return Err(format!("unknown command `{}`, try `help`", cmd)),
+        }
+        Ok(true)
+    }
+}
+
+fn main() -> io::Result<()> {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: intcode-dbg <program file>");
+            std::process::exit(2);
+        }
+    };
+    let code = match parse_program(&std::fs::read_to_string(path)?) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let mut debugger = Debugger {
+        process: Process::with_code(code, BufferIo::default()),
+        addr_breaks: BTreeSet::new(),
+        op_breaks: BTreeSet::new(),
+    };
+    println!("{}", debugger.current());
+    let stdin = io::stdin();
+    loop {
+        print!("(dbg) ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        match debugger.command(&line) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(err) => println!("{}", err),
+        }
+    }
+}
@@ -1,4 +1,50 @@
-use crate::intcode::Process;
+use crate::intcode::search::Search;
+use crate::intcode::symbolic::{evaluate, SymbolicError};
+use crate::intcode::{IntcodeError, Process};
+use std::error::Error;
+use std::fmt;
+
+const TARGET: i64 = 19_690_720;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssistError {
+    Intcode(IntcodeError),
+    /// No noun and verb make the program produce `TARGET`.
+    NoSolution,
+}
+
+impl fmt::Display for AssistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssistError::Intcode(error) => error.fmt(f),
+            AssistError::NoSolution => write!(f, "no noun and verb give {}", TARGET),
+        }
+    }
+}
+
+impl Error for AssistError {}
+
+impl From<IntcodeError> for AssistError {
+    fn from(error: IntcodeError) -> Self {
+        AssistError::Intcode(error)
+    }
+}
+
+/// Why `solve_symbolically` gave up, leaving it to brute force.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SolveError {
+    Symbolic(SymbolicError),
+    /// The program ran, but `code[0]` is no polynomial in noun and verb.
+    NotPolynomial,
+    /// Solving the linear equation for `TARGET` overflows.
+    Overflow,
+}
+
+impl From<SymbolicError> for SolveError {
+    fn from(error: SymbolicError) -> Self {
+        SolveError::Symbolic(error)
+    }
+}
 
 #[aoc_generator(day2)]
 fn get_input(data: &str) -> Process {
@@ -6,26 +52,106 @@ fn get_input(data: &str) -> Process {
 }
 
 #[aoc(day2, part1)]
-fn part1(process: &Process) -> i32 {
+fn part1(process: &Process) -> Result<i64, IntcodeError> {
     let mut process = process.folk();
     process.write(1, 12);
     process.write(2, 2);
-    process.execute();
-    process.code[0]
+    process.execute()?;
+    Ok(process.code[0])
 }
 
-#[aoc(day2, part2)]
-fn part2(process: &Process) -> u32 {
+/// Evaluates the program with noun and verb as unknowns and solves the
+/// resulting expression for `code[0] == TARGET`, directly if it is linear.
+fn solve_symbolically(process: &Process) -> Result<Option<(i64, i64)>, SolveError> {
+    let memory = evaluate(&process.code, &[1, 2])?;
+    let result = memory[0].as_ref().ok_or(SolveError::NotPolynomial)?;
+    if result.degree() > 1 {
+        let mut pairs = (0..=99).flat_map(|noun| (0..=99).map(move |verb| (noun, verb)));
+        return Ok(pairs.find(|&(noun, verb)| result.eval(&[noun, verb]) == Some(TARGET)));
+    }
+    let (a, b, c) = (
+        result.coefficient(&[1]),
+        result.coefficient(&[0, 1]),
+        result.coefficient(&[]),
+    );
     for noun in 0..=99 {
-        for verb in 0..=99 {
-            let mut p = process.folk();
-            p.write(1, noun);
-            p.write(2, verb);
-            p.execute();
-            if p.code[0] == 19690720 {
-                return noun * 100 + verb;
-            }
+        let rest = a
+            .checked_mul(noun)
+            .and_then(|an| TARGET.checked_sub(c)?.checked_sub(an))
+            .ok_or(SolveError::Overflow)?;
+        let verb = match b {
+            0 if rest == 0 => 0,
+            0 => continue,
+            _ if rest.checked_rem(b) == Some(0) => rest / b,
+            _ => continue,
+        };
+        if (0..=99).contains(&verb) {
+            return Ok(Some((noun, verb)));
         }
     }
-    unreachable!()
+    Ok(None)
+}
+
+fn brute_force(process: &Process) -> Result<Option<(i64, i64)>, IntcodeError> {
+    let pairs = (0..=99).flat_map(|noun| (0..=99).map(move |verb| (noun, verb)));
+    Search::new(process).find(pairs, |process, &(noun, verb)| {
+        let mut p = process.folk();
+        p.write(1, noun);
+        p.write(2, verb);
+        p.execute()?;
+        Ok(p.code[0] == TARGET)
+    })
+}
+
+#[aoc(day2, part2)]
+fn part2(process: &Process) -> Result<i64, AssistError> {
+    let found = match solve_symbolically(process) {
+        Ok(found) => found,
+        Err(_) => brute_force(process)?,
+    };
+    let (noun, verb) = found.ok_or(AssistError::NoSolution)?;
+    Ok(noun * 100 + verb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbolic_matches_brute_force() {
+        // code[0] = noun * 100000 + verb + 18490686
+        let process = Process::new("1,0,0,3,2,1,17,0,1,0,2,0,1,0,18,0,99,100000,18490686");
+        assert_eq!(solve_symbolically(&process), Ok(Some((12, 34))));
+        assert_eq!(brute_force(&process), Ok(Some((12, 34))));
+        assert_eq!(part2(&process), Ok(1234));
+
+        // Jumps on the noun, so only brute force can handle it.
+        let process = Process::new("1005,1,3,1,0,2,0,99");
+        assert_eq!(
+            solve_symbolically(&process),
+            Err(SolveError::Symbolic(SymbolicError::Unknown { ip: 0 }))
+        );
+    }
+
+    #[test]
+    fn test_polynomial() {
+        // code[0] = noun * verb * 48000 + 106720, so noun * verb == 408.
+        let process = Process::new("1102,0,0,0,1002,0,48000,0,1001,0,106720,0,99");
+        assert_eq!(solve_symbolically(&process), Ok(Some((6, 68))));
+        assert_eq!(part2(&process), Ok(668));
+    }
+
+    #[test]
+    fn test_fallback() {
+        // code[0] = code[noun] + code[verb] + 20: reads through the unknowns.
+        let process = Process::new("1,0,0,3,1001,3,20,0,99,19690000,700");
+        assert_eq!(solve_symbolically(&process), Err(SolveError::NotPolynomial));
+        assert_eq!(part2(&process), Ok(910));
+
+        // code[0] = noun + verb - i64::MAX can't reach the target, and
+        // solving for it overflows.
+        let process = Process::new("1101,0,0,0,1001,0,-9223372036854775807,0,99");
+        assert_eq!(solve_symbolically(&process), Err(SolveError::Overflow));
+        assert_eq!(part2(&process), Err(AssistError::NoSolution));
+    }
 }
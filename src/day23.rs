@@ -0,0 +1,227 @@
+use crate::intcode::{IntcodeError, Process, Status};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+const COMPUTERS: i64 = 50;
+const NAT: i64 = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingError {
+    Intcode(IntcodeError),
+    /// Computer `from` sent a packet to an address nobody has.
+    InvalidDestination {
+        from: usize,
+        dest: i64,
+    },
+    /// The network went idle with the NAT yet to receive a packet.
+    Idle,
+}
+
+impl fmt::Display for RoutingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoutingError::Intcode(error) => error.fmt(f),
+            RoutingError::InvalidDestination { from, dest } => {
+                write!(f, "computer {} sent a packet to {}", from, dest)
+            }
+            RoutingError::Idle => write!(f, "the NAT never received a packet"),
+        }
+    }
+}
+
+impl Error for RoutingError {}
+
+impl From<IntcodeError> for RoutingError {
+    fn from(error: IntcodeError) -> Self {
+        RoutingError::Intcode(error)
+    }
+}
+
+#[aoc_generator(day23)]
+fn get_input(data: &str) -> Process {
+    Process::new(data)
+}
+
+struct Packet {
+    dest: i64,
+    x: i64,
+    y: i64,
+}
+
+struct Computer {
+    process: Process,
+    partial: Vec<i64>,
+}
+
+impl Computer {
+    /// Runs until the computer asks for input with nothing queued, which it
+    /// is given -1 for once. Returns the packets it sent meanwhile.
+    fn run(&mut self) -> Result<Vec<Packet>, IntcodeError> {
+        let mut sent = vec![];
+        let mut polled = false;
+        loop {
+            match self.process.run()? {
+                Status::Output(value) => {
+                    self.partial.push(value);
+                    if let [dest, x, y] = self.partial[..] {
+                        sent.push(Packet { dest, x, y });
+                        self.partial.clear();
+                    }
+                }
+                Status::AwaitingInput if !polled => {
+                    self.process.input(-1);
+                    polled = true;
+                }
+                Status::AwaitingInput | Status::Halted => return Ok(sent),
+            }
+        }
+    }
+}
+
+struct Network {
+    computers: Vec<Computer>,
+}
+
+impl Network {
+    fn boot(program: &Process) -> Self {
+        let computers = (0..COMPUTERS)
+            .map(|address| {
+                let mut process = program.folk();
+                process.input(address);
+                Computer {
+                    process,
+                    partial: vec![],
+                }
+            })
+            .collect();
+        Self { computers }
+    }
+
+    /// Queues a packet for computer `dest`, returning whether it exists.
+    fn send(&mut self, dest: i64, x: i64, y: i64) -> bool {
+        let computer = usize::try_from(dest)
+            .ok()
+            .and_then(|dest| self.computers.get_mut(dest));
+        match computer {
+            Some(computer) => {
+                computer.process.input(x);
+                computer.process.input(y);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Gives every computer one turn, routing the packets they send.
+    /// Returns the packets addressed to the NAT and whether anything was
+    /// sent at all; if not, every queue is empty and the network is idle.
+    fn tick(&mut self) -> Result<(Vec<(i64, i64)>, bool), RoutingError> {
+        let mut to_nat = vec![];
+        let mut busy = false;
+        for i in 0..self.computers.len() {
+            for packet in self.computers[i].run()? {
+                busy = true;
+                if packet.dest == NAT {
+                    to_nat.push((packet.x, packet.y));
+                } else if !self.send(packet.dest, packet.x, packet.y) {
+                    return Err(RoutingError::InvalidDestination {
+                        from: i,
+                        dest: packet.dest,
+                    });
+                }
+            }
+        }
+        Ok((to_nat, busy))
+    }
+}
+
+#[aoc(day23, part1)]
+fn part1(program: &Process) -> Result<i64, RoutingError> {
+    let mut network = Network::boot(program);
+    loop {
+        let (to_nat, busy) = network.tick()?;
+        if let Some(&(_, y)) = to_nat.first() {
+            return Ok(y);
+        }
+        if !busy {
+            return Err(RoutingError::Idle);
+        }
+    }
+}
+
+#[aoc(day23, part2)]
+fn part2(program: &Process) -> Result<i64, RoutingError> {
+    let mut network = Network::boot(program);
+    let mut nat = None;
+    let mut last_y = None;
+    loop {
+        let (to_nat, busy) = network.tick()?;
+        nat = to_nat.last().copied().or(nat);
+        if busy {
+            continue;
+        }
+        let (x, y) = nat.ok_or(RoutingError::Idle)?;
+        if last_y == Some(y) {
+            return Ok(y);
+        }
+        network.send(0, x, y);
+        last_y = Some(y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+    use crate::intcode::BufferIo;
+
+    fn program(source: &str) -> Process {
+        Process::with_code(assemble(source).unwrap(), BufferIo::default())
+    }
+
+    #[test]
+    fn test_nat() {
+        // Computer 1 sends (3, 7) to the NAT. Computer 0 answers every
+        // packet with one to the NAT, its y counted up to 9.
+        let source = "
+                    in [addr]
+                    eq [addr], #1, [t]
+                    jz [t], recv
+                    out #255
+                    out #3
+                    out #7
+            recv:   in [x]
+                    eq [x], #-1, [t]
+                    jnz [t], recv
+                    in [y]
+                    lt [y], #9, [t]
+                    add [y], [t], [y]
+                    out #255
+                    out [x]
+                    out [y]
+                    jz #0, recv
+            addr:   .data 0
+            x:      .data 0
+            y:      .data 0
+            t:      .data 0
+        ";
+        let program = program(source);
+        assert_eq!(part1(&program), Ok(7));
+        // The NAT delivers 7, 8, 9 and then 9 again.
+        assert_eq!(part2(&program), Ok(9));
+    }
+
+    #[test]
+    fn test_routing_errors() {
+        let idle = program("in [addr]\nhalt\naddr: .data 0");
+        assert_eq!(part1(&idle), Err(RoutingError::Idle));
+        assert_eq!(part2(&idle), Err(RoutingError::Idle));
+
+        let stray = program("out #50\nout #1\nout #2\nhalt");
+        assert_eq!(
+            part1(&stray),
+            Err(RoutingError::InvalidDestination { from: 0, dest: 50 })
+        );
+    }
+}
@@ -179,7 +179,7 @@ fn manhattan_distance(p1: Point, p2: Point) -> u32 {
 
 #[aoc(day3, part1)]
 pub fn closest_cross((w1, w2): &(Wire, Wire)) -> u32 {
-    let mut min_dist = u32::max_value();
+    let mut min_dist = u32::MAX;
     let mut skipped = false;
     for hls in &w1.horizontal_segs {
         for vls in &w2.vertical_segs {
@@ -210,7 +210,7 @@ pub fn closest_cross((w1, w2): &(Wire, Wire)) -> u32 {
 
 #[aoc(day3, part2)]
 pub fn min_step((w1, w2): &(Wire, Wire)) -> u32 {
-    let mut min_step = u32::max_value();
+    let mut min_step = u32::MAX;
     let mut skipped = false;
     for hls in &w1.horizontal_segs {
         for vls in &w2.vertical_segs {
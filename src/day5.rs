@@ -1,21 +1,21 @@
-use crate::intcode::Process;
+use crate::intcode::{IntcodeError, Process};
 #[aoc_generator(day5)]
 fn get_input(data: &str) -> Process {
     Process::new(data)
 }
 
 #[aoc(day5, part1)]
-fn part1(process: &Process) -> i32 {
+fn part1(process: &Process) -> Result<i64, IntcodeError> {
     let mut process = process.folk();
     process.input(1);
-    process.execute();
-    process.output_iter().last().unwrap()
+    process.execute()?;
+    Ok(process.io.output.pop_back().unwrap())
 }
 
 #[aoc(day5, part2)]
-fn part2(process: &Process) -> i32 {
+fn part2(process: &Process) -> Result<i64, IntcodeError> {
     let mut process = process.folk();
     process.input(5);
-    process.execute();
-    process.output_iter().last().unwrap()
+    process.execute()?;
+    Ok(process.io.output.pop_back().unwrap())
 }
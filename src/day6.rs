@@ -68,6 +68,8 @@ impl OrbitMap {
 #[derive(Debug)]
 struct Astral {
     parent: Option<String>,
+    // Only shown in the Debug output, which dead_code does not count.
+    #[allow(dead_code)]
     name: String,
     orbits: Vec<String>,
 }
@@ -1,5 +1,33 @@
-use crate::intcode::Process;
+use crate::intcode::search::Search;
+use crate::intcode::{IntcodeError, Process, Status};
 use itertools::Itertools;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmpError {
+    Intcode(IntcodeError),
+    /// The amplifiers halted without sending a signal, for every phase
+    /// setting.
+    NoSignal,
+}
+
+impl fmt::Display for AmpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmpError::Intcode(error) => error.fmt(f),
+            AmpError::NoSignal => write!(f, "the amplifiers never sent a signal"),
+        }
+    }
+}
+
+impl Error for AmpError {}
+
+impl From<IntcodeError> for AmpError {
+    fn from(error: IntcodeError) -> Self {
+        AmpError::Intcode(error)
+    }
+}
 
 #[aoc_generator(day7)]
 fn get_input(data: &str) -> Process {
@@ -8,15 +36,30 @@ fn get_input(data: &str) -> Process {
 
 struct Amplifier {
     program: Process,
-    phase: i32,
+    phase: i64,
 }
 
 impl Amplifier {
-    fn run(&mut self, input: i32) -> i32 {
+    /// Returns the signal the amplifier sent, if any.
+    fn run(&mut self, input: i64) -> Result<Option<i64>, IntcodeError> {
         self.program.input(self.phase);
         self.program.input(input);
-        self.program.execute();
-        self.program.output().unwrap()
+        self.program.execute()?;
+        Ok(self.program.output())
+    }
+
+    /// Feeds `signals` to the amplifier and runs it until it needs more input
+    /// or halts. Returns the signals it produced and whether it halted.
+    fn resume(&mut self, signals: Vec<i64>) -> Result<(Vec<i64>, bool), IntcodeError> {
+        signals.into_iter().for_each(|s| self.program.input(s));
+        let mut output = vec![];
+        loop {
+            match self.program.run()? {
+                Status::Output(value) => output.push(value),
+                Status::AwaitingInput => return Ok((output, false)),
+                Status::Halted => return Ok((output, true)),
+            }
+        }
     }
 }
 
@@ -25,7 +68,7 @@ struct AmpChain {
 }
 
 impl AmpChain {
-    fn new(program: &Process, phases: Vec<i32>) -> Self {
+    fn new(program: &Process, phases: Vec<i64>) -> Self {
         let amps = phases
             .into_iter()
             .map(|phase| Amplifier {
@@ -36,18 +79,64 @@ impl AmpChain {
         Self { amps }
     }
 
-    fn output(&mut self) -> i32 {
-        self.amps.iter_mut().fold(0, |input, amp| amp.run(input))
+    /// The signal out of the last amplifier, `None` if one of them didn't
+    /// send any.
+    fn output(&mut self) -> Result<Option<i64>, IntcodeError> {
+        self.amps
+            .iter_mut()
+            .try_fold(Some(0), |input, amp| match input {
+                Some(input) => amp.run(input),
+                None => Ok(None),
+            })
+    }
+
+    /// Runs the amplifiers in a ring, E feeding back into A, until all of
+    /// them halt. Returns the last signal E sent, if any.
+    fn feedback_output(&mut self) -> Result<Option<i64>, IntcodeError> {
+        for amp in &mut self.amps {
+            amp.program.input(amp.phase);
+        }
+        let mut signals = vec![0];
+        let mut last = None;
+        loop {
+            let mut all_halted = true;
+            for amp in &mut self.amps {
+                let (output, halted) = amp.resume(signals)?;
+                signals = output;
+                all_halted &= halted;
+            }
+            last = signals.last().copied().or(last);
+            if all_halted {
+                return Ok(last);
+            }
+            if signals.is_empty() {
+                let program = &self.amps[0].program;
+                return Err(IntcodeError::NoInput {
+                    ip: program.ip,
+                    opcode: program.read(program.ip),
+                });
+            }
+        }
     }
 }
 
 #[aoc(day7, part1)]
-fn part1(program: &Process) -> i32 {
-    (0..=4)
-        .permutations(5)
-        .map(|phases| AmpChain::new(program, phases).output())
-        .max()
-        .unwrap()
+fn part1(program: &Process) -> Result<i64, AmpError> {
+    // Phase settings without a signal score `None`, below any signal.
+    let best = Search::new(program).best((0..=4).permutations(5), |program, phases| {
+        AmpChain::new(program, phases.clone()).output()
+    })?;
+    best.and_then(|(_, signal)| signal)
+        .ok_or(AmpError::NoSignal)
+}
+
+#[aoc(day7, part2)]
+fn part2(program: &Process) -> Result<i64, AmpError> {
+    let mut max = None;
+    for phases in (5..=9).permutations(5) {
+        max = max.max(AmpChain::new(program, phases).feedback_output()?);
+    }
+    max.ok_or(AmpError::NoSignal)
 }
 
 #[cfg(test)]
@@ -58,13 +147,38 @@ mod tests {
         let data = "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0";
         let program = get_input(data);
         let mut amp_chain = AmpChain::new(&program, vec![1, 0, 4, 3, 2]);
-        assert_eq!(amp_chain.output(), 65210);
+        assert_eq!(amp_chain.output(), Ok(Some(65210)));
     }
 
     #[test]
     fn test_find_max_output_phase() {
         let data = "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0";
         let program = get_input(data);
-        assert_eq!(part1(&program), 65210);
+        assert_eq!(part1(&program), Ok(65210));
+    }
+
+    #[test]
+    fn test_feedback_loop() {
+        let data =
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+        let program = get_input(data);
+        let mut amp_chain = AmpChain::new(&program, vec![9, 8, 7, 6, 5]);
+        assert_eq!(amp_chain.feedback_output(), Ok(Some(139629729)));
+        assert_eq!(part2(&program), Ok(139629729));
+    }
+
+    #[test]
+    fn test_find_max_feedback_phase() {
+        let data = "3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10";
+        let program = get_input(data);
+        assert_eq!(part2(&program), Ok(18216));
+    }
+
+    #[test]
+    fn test_no_signal() {
+        // Reads the phase setting and halts.
+        let program = get_input("3,0,99");
+        assert_eq!(part1(&program), Err(AmpError::NoSignal));
+        assert_eq!(part2(&program), Err(AmpError::NoSignal));
     }
 }
@@ -1,65 +1,276 @@
+pub mod ascii;
+pub mod asm;
+mod cache;
+pub mod cfg;
+pub mod coverage;
+pub mod disasm;
+mod instruction;
+pub mod io;
+mod limit;
+pub mod network;
+pub mod patch;
+pub mod profile;
+pub mod search;
+mod snapshot;
+pub mod symbolic;
+pub mod task;
+pub mod thread;
+pub mod trace;
+
+use cache::DecodeCache;
+use coverage::Coverage;
+pub use instruction::{Instruction, Parameter, ParameterMode, Params};
+pub use io::{BufferIo, IntcodeIo};
+use patch::PatchLog;
+use profile::Profile;
+pub use snapshot::Snapshot;
+use std::collections::vec_deque::Drain;
 use std::convert::TryInto;
-use std::sync::mpsc::{channel, Receiver, Sender, TryIter, TryRecvError};
-pub struct Process {
-    pub code: Vec<i32>,
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use trace::{IoEvent, MemoryWrite, Trace, TraceEntry};
+
+/// Upper bound on addressable memory, so a stray pointer can't exhaust the host.
+pub const MAX_ADDRESS: i64 = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntcodeError {
+    UnknownOpcode {
+        ip: usize,
+        opcode: i64,
+    },
+    UnknownMode {
+        ip: usize,
+        opcode: i64,
+        mode: i64,
+    },
+    InvalidAddress {
+        ip: usize,
+        opcode: i64,
+        addr: i64,
+    },
+    ImmediateWrite {
+        ip: usize,
+        opcode: i64,
+    },
+    NoInput {
+        ip: usize,
+        opcode: i64,
+    },
+    OutputClosed {
+        ip: usize,
+        opcode: i64,
+    },
+    Overflow {
+        ip: usize,
+        opcode: i64,
+    },
+    StepLimit {
+        ip: usize,
+        opcode: i64,
+        steps: u64,
+    },
+    InfiniteLoop {
+        ip: usize,
+        opcode: i64,
+        entry: usize,
+        cycle_len: u64,
+    },
+}
+
+impl IntcodeError {
+    pub fn ip(&self) -> usize {
+        use IntcodeError::*;
+        match *self {
+            UnknownOpcode { ip, .. }
+            | UnknownMode { ip, .. }
+            | InvalidAddress { ip, .. }
+            | ImmediateWrite { ip, .. }
+            | NoInput { ip, .. }
+            | OutputClosed { ip, .. }
+            | Overflow { ip, .. }
+            | StepLimit { ip, .. }
+            | InfiniteLoop { ip, .. } => ip,
+        }
+    }
+
+    pub fn opcode(&self) -> i64 {
+        use IntcodeError::*;
+        match *self {
+            UnknownOpcode { opcode, .. }
+            | UnknownMode { opcode, .. }
+            | InvalidAddress { opcode, .. }
+            | ImmediateWrite { opcode, .. }
+            | NoInput { opcode, .. }
+            | OutputClosed { opcode, .. }
+            | Overflow { opcode, .. }
+            | StepLimit { opcode, .. }
+            | InfiniteLoop { opcode, .. } => opcode,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use IntcodeError::*;
+        write!(f, "at ip {} (opcode {}): ", self.ip(), self.opcode())?;
+        match *self {
+            UnknownOpcode { .. } => write!(f, "unknown opcode"),
+            UnknownMode { mode, .. } => write!(f, "unknown parameter mode {}", mode),
+            InvalidAddress { addr, .. } => write!(f, "invalid address {}", addr),
+            ImmediateWrite { .. } => write!(f, "write parameter in immediate mode"),
+            NoInput { .. } => write!(f, "no input available"),
+            OutputClosed { .. } => write!(f, "output channel closed"),
+            Overflow { .. } => write!(f, "arithmetic overflow"),
+            StepLimit { steps, .. } => write!(f, "gave up after {} steps", steps),
+            InfiniteLoop {
+                entry, cycle_len, ..
+            } => write!(
+                f,
+                "infinite loop entered at {} repeating every {} steps",
+                entry, cycle_len
+            ),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Halted,
+    AwaitingInput,
+    Output(i64),
+}
+
+pub struct Process<IO = BufferIo> {
+    /// Memory, shared with clones and snapshots until one of them writes.
+    /// Change it through `write`, which keeps decoded instructions up to
+    /// date, or call `clear_decode_cache` after changing it directly.
+    pub code: Arc<Vec<i64>>,
     pub ip: usize,
-    pub input_rx: Receiver<i32>,
-    pub input_tx: Sender<i32>,
-    pub output_rx: Receiver<i32>,
-    pub output_tx: Sender<i32>,
+    pub relative_base: i64,
+    pub io: IO,
+    /// Executed instructions, recorded only while tracing is enabled.
+    pub trace: Option<Trace>,
+    /// Execution counts, collected only while profiling is enabled.
+    pub profile: Option<Profile>,
+    /// Which addresses were used how, collected only while enabled.
+    pub coverage: Option<Coverage>,
+    /// Writes to executed code, recorded only while enabled.
+    pub patches: Option<PatchLog>,
+    cache: DecodeCache,
+}
+
+impl<IO: Clone> Clone for Process<IO> {
+    /// Clones everything but the instrumentation: the copy starts without a
+    /// trace, profile, coverage map or patch log.
+    fn clone(&self) -> Self {
+        Self {
+            code: self.code.clone(),
+            ip: self.ip,
+            relative_base: self.relative_base,
+            io: self.io.clone(),
+            trace: None,
+            profile: None,
+            coverage: None,
+            patches: None,
+            cache: self.cache.clone(),
+        }
+    }
 }
 
 impl Process {
     pub fn new(data: &str) -> Self {
-        let (input_tx, input_rx) = channel();
-        let (output_tx, output_rx) = channel();
+        Self::with_code(
+            data.split(',').map(|n| n.parse::<i64>().unwrap()).collect(),
+            BufferIo::default(),
+        )
+    }
+
+    pub fn input(&mut self, value: i64) {
+        self.io.input.push_back(value)
+    }
+
+    pub fn output(&mut self) -> Option<i64> {
+        self.io.output.pop_front()
+    }
+
+    pub fn output_iter(&mut self) -> Drain<'_, i64> {
+        self.io.output.drain(..)
+    }
+}
+
+impl<IO> Process<IO> {
+    pub fn with_code(code: Vec<i64>, io: IO) -> Self {
         Self {
-            code: data.split(',').map(|n| n.parse::<i32>().unwrap()).collect(),
+            code: Arc::new(code),
             ip: 0,
-            input_tx,
-            input_rx,
-            output_tx,
-            output_rx,
+            relative_base: 0,
+            io,
+            trace: None,
+            profile: None,
+            coverage: None,
+            patches: None,
+            cache: DecodeCache::default(),
         }
     }
-    pub fn folk(&self) -> Self {
-        let (input_tx, input_rx) = channel();
-        let (output_tx, output_rx) = channel();
-        Self {
-            code: self.code.clone(),
+
+    /// Moves the machine, as it is, onto different I/O.
+    pub fn with_io<J>(self, io: J) -> Process<J> {
+        Process {
+            code: self.code,
             ip: self.ip,
-            input_rx,
-            input_tx,
-            output_rx,
-            output_tx,
+            relative_base: self.relative_base,
+            io,
+            trace: self.trace,
+            profile: self.profile,
+            coverage: self.coverage,
+            patches: self.patches,
+            cache: self.cache,
         }
     }
 
-    pub fn input(&mut self, value: i32) {
-        self.input_tx.send(value).unwrap()
+    /// Branches off a copy of the process, including its pending I/O.
+    pub fn folk(&self) -> Self
+    where
+        IO: Clone,
+    {
+        self.clone()
     }
 
-    pub fn output(&mut self) -> Result<i32, TryRecvError> {
-        self.output_rx.try_recv()
+    pub fn read<T: TryInto<usize>>(&self, addr: T) -> i64 {
+        let addr = addr.try_into().ok().unwrap();
+        self.code.get(addr).copied().unwrap_or(0)
     }
 
-    pub fn output_iter(&mut self) -> TryIter<i32> {
-        self.output_rx.try_iter()
+    pub fn indirect_read<T: TryInto<usize>>(&self, addr: T) -> i64 {
+        self.read(self.read(addr))
     }
 
-    pub fn read<T: TryInto<usize>>(&self, addr: T) -> i32 {
-        self.code[addr.try_into().ok().unwrap()]
+    pub fn write<T: TryInto<usize>, V: TryInto<i64>>(&mut self, addr: T, value: V) {
+        let addr = addr.try_into().ok().unwrap();
+        let code = Arc::make_mut(&mut self.code);
+        if addr >= code.len() {
+            code.resize(addr + 1, 0);
+        }
+        code[addr] = value.try_into().ok().unwrap();
+        self.cache.invalidate(addr);
     }
 
-    pub fn indirect_read<T: TryInto<usize>>(&self, addr: T) -> i32 {
-        self.read(self.read(addr))
+    /// Forgets all decoded instructions, as needed after changing `code`
+    /// other than through `write`.
+    pub fn clear_decode_cache(&mut self) {
+        self.cache.clear();
     }
 
-    pub fn write<T: TryInto<usize>, V: TryInto<i32>>(&mut self, addr: T, value: V) {
-        self.code[addr.try_into().ok().unwrap()] = value.try_into().ok().unwrap()
+    /// Turns caching of decoded instructions on (the default) or off.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.cache.set_enabled(enabled);
     }
 
-    pub fn indirect_write<T: TryInto<usize>, V: TryInto<i32>>(&mut self, addr: T, value: V) {
+    pub fn indirect_write<T: TryInto<usize>, V: TryInto<i64>>(&mut self, addr: T, value: V) {
         self.write(self.read(addr), value)
     }
 
@@ -67,82 +278,447 @@ impl Process {
         self.read(self.ip) == 99
     }
 
-    pub fn step(&mut self) {
+    fn address(&self, addr: i64) -> Result<usize, IntcodeError> {
+        if (0..MAX_ADDRESS).contains(&addr) {
+            Ok(addr as usize)
+        } else {
+            Err(IntcodeError::InvalidAddress {
+                ip: self.ip,
+                opcode: self.read(self.ip),
+                addr,
+            })
+        }
+    }
+
+    fn overflow(&self) -> IntcodeError {
+        IntcodeError::Overflow {
+            ip: self.ip,
+            opcode: self.read(self.ip),
+        }
+    }
+
+    fn param_addr(&self, param: Parameter) -> Result<usize, IntcodeError> {
+        match param.mode {
+            ParameterMode::Position => self.address(param.value),
+            ParameterMode::Immediate => Err(IntcodeError::ImmediateWrite {
+                ip: self.ip,
+                opcode: self.read(self.ip),
+            }),
+            // An address past the range of i64 saturates, which is just as
+            // invalid.
+            ParameterMode::Relative => self.address(self.relative_base.saturating_add(param.value)),
+        }
+    }
+
+    fn load(&self, param: Parameter) -> Result<i64, IntcodeError> {
+        match param.mode {
+            ParameterMode::Immediate => Ok(param.value),
+            _ => Ok(self.read(self.param_addr(param)?)),
+        }
+    }
+
+    fn store(&mut self, param: Parameter, value: i64) -> Result<(), IntcodeError> {
+        let addr = self.param_addr(param)?;
+        self.write(addr, value);
+        Ok(())
+    }
+}
+
+impl<IO: IntcodeIo> Process<IO> {
+    /// Starts recording a fresh trace of executed instructions.
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(Trace::default());
+    }
+
+    /// Stops tracing and returns what was recorded.
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// Starts counting executions afresh from the current instruction.
+    pub fn enable_profile(&mut self) {
+        self.profile = Some(Profile::new(self.ip));
+    }
+
+    /// Stops profiling and returns the counts.
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.take()
+    }
+
+    /// Starts a fresh coverage map, counting one run.
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage {
+            runs: 1,
+            ..Coverage::default()
+        });
+    }
+
+    /// Stops collecting coverage and returns the map.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    /// Starts logging writes to addresses executed from now on.
+    pub fn enable_patch_log(&mut self) {
+        self.patches = Some(PatchLog::default());
+    }
+
+    /// Stops logging patches and returns the log.
+    pub fn take_patch_log(&mut self) -> Option<PatchLog> {
+        self.patches.take()
+    }
+
+    /// Executes one instruction, handing back the value of an output instruction
+    /// instead of sending it.
+    fn exec(&mut self) -> Result<Option<i64>, IntcodeError> {
+        if self.profile.is_none() && self.coverage.is_none() && self.patches.is_none() {
+            return self.exec_traced();
+        }
         let ip = self.ip;
-        let op = self.read(ip);
-        let opcode = op % 100;
-        match opcode {
-            1 | 2 | 7 | 8 => {
-                let oprand1 = if op / 100 % 10 == 1 {
-                    self.read(ip + 1)
-                } else {
-                    self.indirect_read(ip + 1)
-                };
-                let oprand2 = if op / 1000 % 10 == 1 {
-                    self.read(ip + 2)
+        let instruction = self.cache.decode(&self.code, ip)?;
+        // Operand addresses depend on the relative base, which the
+        // instruction itself may change.
+        let addrs: Vec<_> = if self.coverage.is_some() || self.patches.is_some() {
+            instruction
+                .params()
+                .into_iter()
+                .map(|param| match param.mode {
+                    ParameterMode::Immediate => None,
+                    _ => self.param_addr(param).ok(),
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        let dest = instruction.dest_index().and_then(|i| *addrs.get(i)?);
+        let old = dest.map(|addr| self.read(addr));
+        let output = self.exec_traced()?;
+        let write = dest.map(|addr| MemoryWrite {
+            addr,
+            old: old.unwrap(),
+            new: self.read(addr),
+        });
+        if let Some(profile) = &mut self.profile {
+            profile.record(ip, instruction, self.ip);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(ip, instruction, &addrs, self.ip);
+        }
+        if let Some(patches) = &mut self.patches {
+            patches.record(ip, instruction, write);
+        }
+        Ok(output)
+    }
+
+    fn exec_traced(&mut self) -> Result<Option<i64>, IntcodeError> {
+        if self.trace.is_none() {
+            return self.exec_instruction();
+        }
+        let ip = self.ip;
+        let opcode = self.read(ip);
+        let instruction = self.cache.decode(&self.code, ip)?;
+        let dest_index = instruction.dest_index();
+        // Operands the instruction doesn't get to use may not load; it fails
+        // itself if one it uses doesn't.
+        let mut operands: Vec<_> = instruction
+            .params()
+            .into_iter()
+            .enumerate()
+            .map(|(i, param)| {
+                if Some(i) == dest_index {
+                    self.param_addr(param).map(|addr| addr as i64).ok()
                 } else {
-                    self.indirect_read(ip + 2)
-                };
-                let value = match opcode {
-                    1 => oprand1 + oprand2,
-                    2 => oprand1 * oprand2,
-                    7 => {
-                        if oprand1 < oprand2 {
-                            1
-                        } else {
-                            0
-                        }
-                    }
-                    8 => {
-                        if oprand1 == oprand2 {
-                            1
-                        } else {
-                            0
-                        }
-                    }
-                    _ => unreachable!(),
-                };
-                self.indirect_write(ip + 3, value);
-                self.ip += 4;
+                    self.load(param).ok()
+                }
+            })
+            .collect();
+        let dest = dest_index
+            .and_then(|i| operands[i])
+            .map(|addr| addr as usize);
+        let old = dest.map(|addr| self.read(addr));
+        let output = self.exec_instruction()?;
+        if let Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) = instruction {
+            let taken = operands[0].is_some_and(|cond| {
+                (cond != 0) == matches!(instruction, Instruction::JumpIfTrue(..))
+            });
+            if !taken {
+                operands[1] = None;
+            }
+        }
+        let write = dest.map(|addr| MemoryWrite {
+            addr,
+            old: old.unwrap(),
+            new: self.read(addr),
+        });
+        let io = match instruction {
+            Instruction::In(_) => write.map(|write| IoEvent::Input(write.new)),
+            Instruction::Out(_) => output.map(IoEvent::Output),
+            _ => None,
+        };
+        if let Some(trace) = &mut self.trace {
+            trace.entries.push(TraceEntry {
+                ip,
+                opcode,
+                operands,
+                write,
+                io,
+            });
+        }
+        Ok(output)
+    }
+
+    fn exec_instruction(&mut self) -> Result<Option<i64>, IntcodeError> {
+        use Instruction::*;
+        let ip = self.ip;
+        let instruction = self.cache.decode(&self.code, ip)?;
+        let mut output = None;
+        let mut next = ip + instruction.size();
+        match instruction {
+            Add(a, b, dest) => {
+                let value = self.load(a)?.checked_add(self.load(b)?);
+                self.store(dest, value.ok_or_else(|| self.overflow())?)?
             }
-            3 => {
-                let input = self.input_rx.try_recv().unwrap();
-                self.indirect_write(ip + 1, input);
-                self.ip += 2;
+            Mul(a, b, dest) => {
+                let value = self.load(a)?.checked_mul(self.load(b)?);
+                self.store(dest, value.ok_or_else(|| self.overflow())?)?
             }
-            4 => {
-                let output = if op / 100 % 10 == 1 {
-                    self.read(ip + 1)
-                } else {
-                    self.indirect_read(ip + 1)
-                };
-                self.output_tx.send(output).unwrap();
-                self.ip += 2;
+            LessThan(a, b, dest) => {
+                let value = if self.load(a)? < self.load(b)? { 1 } else { 0 };
+                self.store(dest, value)?
             }
-            5 | 6 => {
-                let cond = if op / 100 % 10 == 1 {
-                    self.read(ip + 1)
-                } else {
-                    self.indirect_read(ip + 1)
-                };
-                if (opcode == 5 && cond != 0) || (opcode == 6 && cond == 0) {
-                    let addr = if op / 1000 % 10 == 1 {
-                        self.read(ip + 2)
-                    } else {
-                        self.indirect_read(ip + 2)
-                    };
-                    self.ip = addr as usize;
-                } else {
-                    self.ip += 3;
+            Equals(a, b, dest) => {
+                let value = if self.load(a)? == self.load(b)? { 1 } else { 0 };
+                self.store(dest, value)?
+            }
+            In(dest) => match self.io.read_input() {
+                Some(input) => self.store(dest, input)?,
+                None => {
+                    return Err(IntcodeError::NoInput {
+                        ip,
+                        opcode: self.read(ip),
+                    })
+                }
+            },
+            Out(a) => output = Some(self.load(a)?),
+            JumpIfTrue(cond, target) | JumpIfFalse(cond, target) => {
+                let cond = self.load(cond)?;
+                if (cond != 0) == matches!(instruction, JumpIfTrue(..)) {
+                    let target = self.load(target)?;
+                    next = self.address(target)?;
+                    self.cache.jumped(ip, next);
                 }
             }
-            _ => unreachable!(),
+            AdjustRelativeBase(a) => {
+                let base = self.relative_base.checked_add(self.load(a)?);
+                self.relative_base = base.ok_or_else(|| self.overflow())?
+            }
+            Halt => next = ip,
+        }
+        self.ip = next;
+        Ok(output)
+    }
+
+    /// Notes reaching the `halt` at `ip`, which `run` and `execute` stop in
+    /// front of instead of executing.
+    fn record_halt(&mut self) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(self.ip, Instruction::Halt, &[], self.ip);
+        }
+    }
+
+    pub fn step(&mut self) -> Result<(), IntcodeError> {
+        let ip = self.ip;
+        if let Some(output) = self.exec()? {
+            self.io
+                .write_output(output)
+                .map_err(|_| IntcodeError::OutputClosed {
+                    ip,
+                    opcode: self.read(ip),
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Runs until the program halts, produces an output or needs input that
+    /// hasn't been queued yet. The returned output is not passed on to `io`.
+    /// After `AwaitingInput` the process can be resumed once input is queued.
+    pub fn run(&mut self) -> Result<Status, IntcodeError> {
+        while !self.is_finished() {
+            match self.exec() {
+                Ok(Some(output)) => return Ok(Status::Output(output)),
+                Ok(None) => (),
+                Err(IntcodeError::NoInput { .. }) => return Ok(Status::AwaitingInput),
+                Err(err) => return Err(err),
+            }
         }
+        self.record_halt();
+        Ok(Status::Halted)
     }
 
-    pub fn execute(&mut self) {
+    pub fn execute(&mut self) -> Result<(), IntcodeError> {
         while !self.is_finished() {
-            self.step()
+            self.step()?;
+        }
+        self.record_halt();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_memory_grows_on_write() {
+        let mut process = Process::new("1101,100000000,100000000,10,99");
+        process.execute().unwrap();
+        assert_eq!(process.code.len(), 11);
+        assert_eq!(process.read(10), 200_000_000);
+        assert_eq!(process.read(1000), 0);
+    }
+
+    #[test]
+    fn test_large_product() {
+        let mut process = Process::new("1102,34915192,34915192,7,4,7,99,0");
+        process.execute().unwrap();
+        assert_eq!(process.output(), Some(1_219_070_632_396_864));
+    }
+
+    #[test]
+    fn test_quine() {
+        let data = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut process = Process::new(data);
+        process.execute().unwrap();
+        let output = process.output_iter().map(|n| n.to_string()).join(",");
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn test_relative_write() {
+        let mut process = Process::new("109,10,21101,3,4,5,204,5,99");
+        process.execute().unwrap();
+        assert_eq!(process.read(15), 7);
+        assert_eq!(process.output(), Some(7));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut process = Process::new("1,0,0,0,42");
+        assert_eq!(
+            process.execute(),
+            Err(IntcodeError::UnknownOpcode { ip: 4, opcode: 42 })
+        );
+        let mut process = Process::new("3,0,99");
+        assert_eq!(
+            process.execute(),
+            Err(IntcodeError::NoInput { ip: 0, opcode: 3 })
+        );
+        let mut process = Process::new("11101,1,1,0,99");
+        assert_eq!(
+            process.execute(),
+            Err(IntcodeError::ImmediateWrite {
+                ip: 0,
+                opcode: 11101
+            })
+        );
+        let mut process = Process::new("4,-1,99");
+        assert_eq!(
+            process.execute(),
+            Err(IntcodeError::InvalidAddress {
+                ip: 0,
+                opcode: 4,
+                addr: -1
+            })
+        );
+        // A `halt` with mode digits isn't one, so it can't loop in place.
+        for &(data, mode) in &[("199", 1), ("10099", 1), ("20099", 2)] {
+            assert_eq!(
+                Process::new(data).execute(),
+                Err(IntcodeError::UnknownMode {
+                    ip: 0,
+                    opcode: data.parse().unwrap(),
+                    mode
+                })
+            );
         }
+        let mut process = Process::new("1101,9223372036854775807,1,0,99");
+        assert_eq!(
+            process.execute(),
+            Err(IntcodeError::Overflow {
+                ip: 0,
+                opcode: 1101
+            })
+        );
+        let mut process = Process::new("1102,-9223372036854775808,-1,0,99");
+        assert_eq!(
+            process.execute(),
+            Err(IntcodeError::Overflow {
+                ip: 0,
+                opcode: 1102
+            })
+        );
+    }
+
+    #[test]
+    fn test_run_until_blocked() {
+        let mut process = Process::new("3,9,1002,9,2,9,4,9,99,0");
+        assert_eq!(process.run(), Ok(Status::AwaitingInput));
+        assert_eq!(process.ip, 0);
+        process.input(21);
+        assert_eq!(process.run(), Ok(Status::Output(42)));
+        assert_eq!(process.run(), Ok(Status::Halted));
+        assert_eq!(process.output(), None);
+    }
+
+    #[test]
+    fn test_folk_drops_instrumentation() {
+        let mut process = Process::new("3,9,1002,9,3,9,4,9,99,0");
+        process.enable_trace();
+        process.enable_profile();
+        process.enable_coverage();
+        process.enable_patch_log();
+        process.input(5);
+        process.step().unwrap();
+        let mut branch = process.folk();
+        assert!(branch.trace.is_none() && branch.profile.is_none());
+        assert!(branch.coverage.is_none() && branch.patches.is_none());
+        branch.execute().unwrap();
+        assert_eq!(branch.output(), Some(15));
+        assert_eq!(process.take_trace().unwrap().entries.len(), 1);
+    }
+
+    #[test]
+    fn test_trace_keeps_behaviour() {
+        // The jump isn't taken, so its invalid target is never read.
+        let data = "106,1,-1,104,7,99";
+        let mut plain = Process::new(data);
+        plain.execute().unwrap();
+        let mut traced = Process::new(data);
+        traced.enable_trace();
+        traced.execute().unwrap();
+        assert_eq!(traced.output(), plain.output());
+        let trace = traced.take_trace().unwrap();
+        assert_eq!(trace.entries[0].operands, vec![Some(1), None]);
+        assert!(trace.entries[0]
+            .to_json()
+            .contains(r#""operands":[1,null]"#));
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut process = Process::new("3,9,1002,9,3,9,4,9,99,0");
+        process.enable_trace();
+        process.input(5);
+        process.execute().unwrap();
+        let mut out = vec![];
+        process.take_trace().unwrap().write_jsonl(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"ip":0,"opcode":3,"operands":[9],"write":{"addr":9,"old":0,"new":5},"input":5}
+{"ip":2,"opcode":1002,"operands":[5,3,9],"write":{"addr":9,"old":5,"new":15}}
+{"ip":6,"opcode":4,"operands":[15],"output":15}
+"#
+        );
     }
 }
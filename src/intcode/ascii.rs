@@ -0,0 +1,84 @@
+//! Helpers for programs that talk in ASCII text.
+
+use super::Process;
+
+fn is_ascii(value: i64) -> bool {
+    (0..128).contains(&value)
+}
+
+fn to_char(value: i64) -> char {
+    if is_ascii(value) {
+        value as u8 as char
+    } else {
+        char::REPLACEMENT_CHARACTER
+    }
+}
+
+/// Splits output into its text and a trailing value outside the ASCII range,
+/// which is how programs usually report the final answer.
+pub fn split_answer(output: &[i64]) -> (String, Option<i64>) {
+    match output.split_last() {
+        Some((&answer, text)) if !is_ascii(answer) => {
+            (text.iter().copied().map(to_char).collect(), Some(answer))
+        }
+        _ => (output.iter().copied().map(to_char).collect(), None),
+    }
+}
+
+impl Process {
+    /// Queues `line` as character codes, followed by a newline.
+    pub fn input_line(&mut self, line: &str) {
+        for byte in line.bytes().chain(Some(b'\n')) {
+            self.input(byte as i64);
+        }
+    }
+
+    /// Takes the complete lines of text from pending output. An unfinished
+    /// line, or anything from the first non-ASCII value on, stays queued.
+    pub fn output_lines(&mut self) -> Vec<String> {
+        let output = &mut self.io.output;
+        let text_len = output
+            .iter()
+            .position(|&value| !is_ascii(value))
+            .unwrap_or(output.len());
+        let end = match output.range(..text_len).rposition(|&value| value == 10) {
+            Some(newline) => newline + 1,
+            None => return vec![],
+        };
+        let text: String = output.drain(..end).map(to_char).collect();
+        text.lines().map(str::to_owned).collect()
+    }
+
+    /// Takes all pending output, split as by `split_answer`.
+    pub fn output_text_and_answer(&mut self) -> (String, Option<i64>) {
+        let output: Vec<i64> = self.output_iter().collect();
+        split_answer(&output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_output() {
+        let mut process = Process::new("99");
+        let text = "Hi\nthere\npartial";
+        process.io.output.extend(text.bytes().map(|b| b as i64));
+        assert_eq!(process.output_lines(), vec!["Hi", "there"]);
+        process.io.output.push_back(19_690_720);
+        assert_eq!(process.output_lines(), Vec::<String>::new());
+        assert_eq!(
+            process.output_text_and_answer(),
+            ("partial".to_owned(), Some(19_690_720))
+        );
+    }
+
+    #[test]
+    fn test_input_line() {
+        let mut process = Process::new("3,0,4,0,3,0,4,0,3,0,4,0,99");
+        process.input_line("ok");
+        process.execute().unwrap();
+        assert_eq!(process.output_lines(), vec!["ok"]);
+    }
+}
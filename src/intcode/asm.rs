@@ -0,0 +1,379 @@
+//! Assembler for the syntax printed by the disassembler:
+//!
+//! ```text
+//! ; comments run to the end of the line
+//! start:  in [count]              ; position mode
+//!         add [count], #-1, [rb+2] ; immediate and relative modes
+//!         jnz [count], start      ; a bare operand is immediate
+//!         halt
+//! count:  .data 0, 0
+//! ```
+
+use super::{Instruction, Parameter, ParameterMode};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+#[derive(Debug)]
+enum Value {
+    Number(i64),
+    Label(String),
+}
+
+#[derive(Debug)]
+struct Operand {
+    mode: ParameterMode,
+    value: Value,
+    column: usize,
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction {
+        mnemonic: String,
+        operands: Vec<Operand>,
+    },
+    Data(Vec<Operand>),
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction { operands, .. } => operands.len() + 1,
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+struct Cursor {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Cursor {
+    fn new(line: usize, text: &str) -> Self {
+        let text = text.split(';').next().unwrap();
+        Self {
+            chars: text.chars().collect(),
+            pos: 0,
+            line,
+        }
+    }
+
+    fn column(&self) -> usize {
+        self.pos + 1
+    }
+
+    fn error<T>(&self, column: usize, message: impl Into<String>) -> Result<T, AsmError> {
+        Err(AsmError {
+            line: self.line,
+            column,
+            message: message.into(),
+        })
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_sign(&mut self) -> bool {
+        self.skip_whitespace();
+        self.peek() == Some('-')
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.peek().is_none()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), AsmError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            self.error(self.column(), format!("expected `{}`", c))
+        }
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c == '_' || c == '.' || c.is_ascii_alphanumeric())
+        {
+            if self.pos == start && self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                break;
+            }
+            self.pos += 1;
+        }
+        if self.pos > start {
+            Some(self.chars[start..self.pos].iter().collect())
+        } else {
+            None
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, AsmError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if let Some(label) = self.ident() {
+            return Ok(Value::Label(label));
+        }
+        if let Some('-') | Some('+') = self.peek() {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        match text.parse() {
+            Ok(n) => Ok(Value::Number(n)),
+            Err(_) => self.error(start + 1, "expected a number or label"),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, AsmError> {
+        self.skip_whitespace();
+        let column = self.column();
+        let (mode, value) = if self.eat('#') {
+            (ParameterMode::Immediate, self.value()?)
+        } else if self.eat('[') {
+            let save = self.pos;
+            let operand = if self.ident().as_deref() == Some("rb") {
+                let value = if self.eat('+') || self.peek_sign() {
+                    self.value()?
+                } else {
+                    Value::Number(0)
+                };
+                (ParameterMode::Relative, value)
+            } else {
+                self.pos = save;
+                (ParameterMode::Position, self.value()?)
+            };
+            self.expect(']')?;
+            operand
+        } else {
+            (ParameterMode::Immediate, self.value()?)
+        };
+        Ok(Operand {
+            mode,
+            value,
+            column,
+        })
+    }
+
+    fn operands(&mut self) -> Result<Vec<Operand>, AsmError> {
+        let mut operands = vec![];
+        if self.at_end() {
+            return Ok(operands);
+        }
+        loop {
+            operands.push(self.operand()?);
+            if self.at_end() {
+                return Ok(operands);
+            }
+            self.expect(',')?;
+        }
+    }
+}
+
+/// Assembles `source` into the memory image `Process` runs.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut statements = vec![];
+    let mut addr = 0;
+    for (n, text) in source.lines().enumerate() {
+        let mut cursor = Cursor::new(n + 1, text);
+        let mut column = 0;
+        let mut word = None;
+        while !cursor.at_end() {
+            column = cursor.column();
+            word = cursor.ident();
+            if word.is_none() {
+                return cursor.error(column, "expected a label or mnemonic");
+            }
+            if !cursor.eat(':') {
+                break;
+            }
+            let label = word.take().unwrap();
+            if labels.insert(label.clone(), addr).is_some() {
+                return cursor.error(column, format!("duplicate label `{}`", label));
+            }
+        }
+        let mnemonic = match word {
+            Some(mnemonic) => mnemonic,
+            None => continue,
+        };
+        let statement = if mnemonic == ".data" {
+            let values = cursor.operands()?;
+            if let Some(operand) = values
+                .iter()
+                .find(|operand| operand.mode != ParameterMode::Immediate)
+            {
+                return cursor.error(operand.column, "`.data` takes plain values");
+            }
+            Statement::Data(values)
+        } else {
+            let operands = cursor.operands()?;
+            let any = [Parameter {
+                mode: ParameterMode::Position,
+                value: 0,
+            }; 3];
+            if operands.len() > 3
+                || Instruction::from_parts(&mnemonic, &any[..operands.len()]).is_none()
+            {
+                let known =
+                    (0..=3).any(|n| Instruction::from_parts(&mnemonic, &any[..n]).is_some());
+                let message = if known {
+                    format!("wrong number of operands for `{}`", mnemonic)
+                } else {
+                    format!("unknown mnemonic `{}`", mnemonic)
+                };
+                return cursor.error(column, message);
+            }
+            Statement::Instruction { mnemonic, operands }
+        };
+        addr += statement.size();
+        statements.push((n + 1, statement));
+    }
+
+    let mut code = Vec::with_capacity(addr);
+    for (line, statement) in statements {
+        let error = |column, message: String| AsmError {
+            line,
+            column,
+            message,
+        };
+        let resolve = |operand: &Operand| match &operand.value {
+            Value::Number(n) => Ok(*n),
+            Value::Label(label) => labels
+                .get(label)
+                .map(|&addr| addr as i64)
+                .ok_or_else(|| error(operand.column, format!("undefined label `{}`", label))),
+        };
+        match statement {
+            Statement::Data(values) => {
+                for value in &values {
+                    code.push(resolve(value)?);
+                }
+            }
+            Statement::Instruction { mnemonic, operands } => {
+                let params = operands
+                    .iter()
+                    .map(|operand| {
+                        Ok(Parameter {
+                            mode: operand.mode,
+                            value: resolve(operand)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, AsmError>>()?;
+                let instruction = Instruction::from_parts(&mnemonic, &params).unwrap();
+                if let Some(dest) = instruction.dest_index() {
+                    if params[dest].mode == ParameterMode::Immediate {
+                        return Err(error(
+                            operands[dest].column,
+                            format!("`{}` can't write to an immediate operand", mnemonic),
+                        ));
+                    }
+                }
+                code.extend(instruction.encode());
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// Assembles `source` into the comma separated form `Process::new` parses.
+pub fn assemble_to_string(source: &str) -> Result<String, AsmError> {
+    Ok(assemble(source)?.iter().join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::Process;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; doubles its input
+            start:  in [value]
+                    mul [value], #2, [value]
+                    out [value]
+                    jz #0, end      ; forward reference
+                    .data 7
+            end:    halt
+            value:  .data 0
+        ";
+        let code = assemble(source).unwrap();
+        assert_eq!(
+            code,
+            vec![3, 13, 1002, 13, 2, 13, 4, 13, 1106, 0, 12, 7, 99, 0]
+        );
+        let mut process = Process::new(&assemble_to_string(source).unwrap());
+        process.input(21);
+        process.execute().unwrap();
+        assert_eq!(process.output(), Some(42));
+    }
+
+    #[test]
+    fn test_relative_operands() {
+        let code = assemble("arb #10\nadd [rb-2], [rb], [rb+3]\nhalt").unwrap();
+        assert_eq!(code, vec![109, 10, 22201, -2, 0, 3, 99]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source| assemble(source).unwrap_err();
+        assert_eq!(
+            error("add #1, #2, #3"),
+            AsmError {
+                line: 1,
+                column: 13,
+                message: "`add` can't write to an immediate operand".to_owned()
+            }
+        );
+        assert_eq!(error("halt\n  jz #0, nowhere").column, 10);
+        assert_eq!(error("halt\n  jz #0, nowhere").line, 2);
+        assert_eq!(error("x: halt\n  x: halt").message, "duplicate label `x`");
+        assert_eq!(
+            error("out #1, #2").message,
+            "wrong number of operands for `out`"
+        );
+        assert_eq!(error("  mov #1").column, 3);
+        assert_eq!(error("out [1").column, 7);
+    }
+}
@@ -0,0 +1,105 @@
+use super::{Instruction, IntcodeError};
+
+/// Longest instruction, in words.
+const MAX_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Slot {
+    /// The instruction starting here.
+    instruction: Option<Instruction>,
+    /// Whether any cached instruction may cover this word.
+    covered: bool,
+}
+
+/// Decoded instructions per address, so loops decode each instruction once.
+/// `Process::write` invalidates the entries a write overlaps; writes to plain
+/// data only cost a lookup.
+///
+/// Caching only starts with the first backward jump: straight-line runs,
+/// like day 2, execute every instruction once and would only pay for it.
+/// Such programs therefore run no faster with the cache; only loops gain.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeCache {
+    slots: Vec<Slot>,
+    looping: bool,
+    disabled: bool,
+}
+
+impl DecodeCache {
+    pub fn decode(&mut self, code: &[i64], ip: usize) -> Result<Instruction, IntcodeError> {
+        if let Some(Slot {
+            instruction: Some(instruction),
+            ..
+        }) = self.slots.get(ip)
+        {
+            return Ok(*instruction);
+        }
+        let instruction = Instruction::decode(code, ip)?;
+        if self.looping && !self.disabled {
+            let end = ip + instruction.size();
+            if end > self.slots.len() {
+                self.slots.resize(code.len().max(end), Slot::default());
+            }
+            self.slots[ip].instruction = Some(instruction);
+            for slot in &mut self.slots[ip..end] {
+                slot.covered = true;
+            }
+        }
+        Ok(instruction)
+    }
+
+    pub fn jumped(&mut self, from: usize, to: usize) {
+        if to <= from {
+            self.looping = true;
+        }
+    }
+
+    /// Drops the entries of instructions covering `addr`.
+    pub fn invalidate(&mut self, addr: usize) {
+        if !self.slots.get(addr).is_some_and(|slot| slot.covered) {
+            return;
+        }
+        let start = (addr + 1).saturating_sub(MAX_SIZE);
+        for (ip, slot) in self.slots[..=addr].iter_mut().enumerate().skip(start) {
+            if slot
+                .instruction
+                .is_some_and(|instruction| ip + instruction.size() > addr)
+            {
+                slot.instruction = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.disabled = !enabled;
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::Process;
+
+    #[test]
+    fn test_writes_invalidate() {
+        // Counts by incrementing the operand of its own `out` instruction.
+        let data = "104,0,1001,1,1,1,1007,1,3,14,1005,14,0,99,0";
+        for &enabled in &[true, false] {
+            let mut process = Process::new(data);
+            process.set_decode_cache(enabled);
+            process.execute().unwrap();
+            assert_eq!(process.output_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+        }
+
+        let mut process = Process::new(data);
+        process.execute().unwrap();
+        let snapshot = Process::new(data).snapshot();
+        process.restore(&snapshot);
+        process.execute().unwrap();
+        assert_eq!(process.output_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,206 @@
+//! Static control-flow graph of a program, built from its disassembly.
+
+use super::disasm::{disassemble, label, Listing};
+use super::{Instruction, ParameterMode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    /// Falling through into a block that starts right after this one.
+    Next,
+    /// A jump, conditional or not.
+    Taken,
+    /// Falling through past a conditional jump.
+    NotTaken,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    /// The address right after the last instruction.
+    pub end: usize,
+    pub instructions: Vec<(usize, Instruction)>,
+    pub successors: Vec<(usize, EdgeKind)>,
+    /// Whether the block ends in a jump whose target is read from memory.
+    pub unresolved: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, BasicBlock>,
+    /// As in `Listing::undecodable`.
+    pub undecodable: BTreeSet<usize>,
+}
+
+/// Splits what `disassemble` reaches from address 0 into basic blocks. A
+/// block starts at address 0, at every immediate jump target and after
+/// every jump, and ends at a jump, a `halt` or before the next block.
+pub fn control_flow_graph(code: &[i64]) -> Cfg {
+    let Listing {
+        entries,
+        labels,
+        undecodable,
+        ..
+    } = disassemble(code);
+    let instructions: BTreeMap<usize, Instruction> = entries
+        .iter()
+        .filter_map(|entry| entry.instruction.map(|i| (entry.addr, i)))
+        .collect();
+    let mut leaders: BTreeSet<usize> = labels;
+    leaders.insert(0);
+    for (&addr, instruction) in &instructions {
+        if is_jump(instruction) {
+            leaders.insert(addr + instruction.size());
+        }
+    }
+
+    let mut blocks = BTreeMap::new();
+    for &start in &leaders {
+        let mut block = BasicBlock {
+            start,
+            end: start,
+            instructions: vec![],
+            successors: vec![],
+            unresolved: false,
+        };
+        let mut addr = start;
+        while let Some(&instruction) = instructions.get(&addr) {
+            block.instructions.push((addr, instruction));
+            addr += instruction.size();
+            block.end = addr;
+            if is_jump(&instruction) || instruction == Instruction::Halt {
+                break;
+            }
+            if leaders.contains(&addr) {
+                block.successors.push((addr, EdgeKind::Next));
+                break;
+            }
+        }
+        if block.instructions.is_empty() {
+            continue;
+        }
+        let (_, last) = *block.instructions.last().unwrap();
+        match last {
+            Instruction::JumpIfTrue(cond, target) | Instruction::JumpIfFalse(cond, target) => {
+                let jump_on = matches!(last, Instruction::JumpIfTrue(..));
+                let constant = cond.mode == ParameterMode::Immediate;
+                let always = constant && (cond.value != 0) == jump_on;
+                let never = constant && !always;
+                if !never {
+                    if target.mode == ParameterMode::Immediate && target.value >= 0 {
+                        block
+                            .successors
+                            .push((target.value as usize, EdgeKind::Taken));
+                    } else {
+                        block.unresolved = true;
+                    }
+                }
+                if !always {
+                    block.successors.push((block.end, EdgeKind::NotTaken));
+                }
+            }
+            Instruction::Halt => (),
+            _ if block.successors.is_empty() => {
+                // Ran into data or a word that doesn't decode.
+                block.successors.push((block.end, EdgeKind::Next));
+            }
+            _ => (),
+        }
+        blocks.insert(start, block);
+    }
+    Cfg {
+        blocks,
+        undecodable,
+    }
+}
+
+fn is_jump(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Cfg {
+    /// Renders the graph in Graphviz DOT. Unresolved jumps point to a
+    /// dashed `?` node, undecodable targets are drawn dashed as well.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph cfg {\n    node [shape=box, fontname=monospace];\n");
+        for block in self.blocks.values() {
+            let mut text = format!("{}:\\l", label(block.start));
+            for (addr, instruction) in &block.instructions {
+                text += &escape(&format!("{:04}  {}", addr, instruction));
+                text += "\\l";
+            }
+            writeln!(out, "    {} [label=\"{}\"];", label(block.start), text).unwrap();
+        }
+        for &addr in &self.undecodable {
+            writeln!(
+                out,
+                "    {} [label=\"{}: not decodable\", style=dashed];",
+                label(addr),
+                label(addr)
+            )
+            .unwrap();
+        }
+        if self.blocks.values().any(|block| block.unresolved) {
+            out += "    unresolved [label=\"?\", shape=circle, style=dashed];\n";
+        }
+        for block in self.blocks.values() {
+            for &(to, kind) in &block.successors {
+                let attrs = match kind {
+                    EdgeKind::Next => "",
+                    EdgeKind::Taken => " [color=darkgreen]",
+                    EdgeKind::NotTaken => " [color=red]",
+                };
+                writeln!(out, "    {} -> {}{};", label(block.start), label(to), attrs).unwrap();
+            }
+            if block.unresolved {
+                writeln!(
+                    out,
+                    "    {} -> unresolved [style=dashed];",
+                    label(block.start)
+                )
+                .unwrap();
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_flow_graph() {
+        // in [14]; jnz [14], L9; out #0; halt; L9: jz #0, [14]; .data 0
+        let code = [3, 14, 1005, 14, 8, 104, 0, 99, 106, 0, 14, 99, 99, 99, 0];
+        let cfg = control_flow_graph(&code);
+        assert_eq!(
+            cfg.blocks.keys().copied().collect::<Vec<_>>(),
+            vec![0, 5, 8]
+        );
+        assert_eq!(
+            cfg.blocks[&0].successors,
+            vec![(8, EdgeKind::Taken), (5, EdgeKind::NotTaken)]
+        );
+        assert_eq!(cfg.blocks[&0].end, 5);
+        assert!(cfg.blocks[&5].successors.is_empty());
+        assert!(cfg.blocks[&8].unresolved);
+        assert!(cfg.blocks[&8].successors.is_empty());
+
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph cfg {\n"));
+        assert!(dot.contains("    L0 -> L8 [color=darkgreen];\n"));
+        assert!(dot.contains("    L0 -> L5 [color=red];\n"));
+        assert!(dot.contains("    L8 -> unresolved [style=dashed];\n"));
+        assert!(dot.contains("0005  out #0\\l"));
+    }
+}
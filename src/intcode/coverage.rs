@@ -0,0 +1,197 @@
+use super::disasm::disassemble;
+use super::Instruction;
+
+/// How one address was used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Executed as the opcode of an instruction.
+    pub opcode: bool,
+    /// Executed as a parameter of an instruction.
+    pub operand: bool,
+    /// Read as data through a position or relative mode parameter.
+    pub read: bool,
+    pub written: bool,
+}
+
+impl Usage {
+    pub fn is_executed(&self) -> bool {
+        self.opcode || self.operand
+    }
+
+    pub fn is_untouched(&self) -> bool {
+        *self == Usage::default()
+    }
+
+    fn merge(&mut self, other: Usage) {
+        self.opcode |= other.opcode;
+        self.operand |= other.operand;
+        self.read |= other.read;
+        self.written |= other.written;
+    }
+
+    /// `x` opcode, `o` operand, `r` read, `w` written, `-` untouched.
+    fn tag(&self) -> String {
+        let tag: String = [
+            (self.opcode, 'x'),
+            (self.operand, 'o'),
+            (self.read, 'r'),
+            (self.written, 'w'),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|&(_, c)| c)
+        .collect();
+        if tag.is_empty() {
+            "-".to_owned()
+        } else {
+            tag
+        }
+    }
+}
+
+/// Per-address usage over any number of runs, none by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    pub usage: Vec<Usage>,
+    pub runs: u64,
+}
+
+impl Coverage {
+    /// Usage of `addr`, untouched if never seen.
+    pub fn get(&self, addr: usize) -> Usage {
+        self.usage.get(addr).copied().unwrap_or_default()
+    }
+
+    fn mark(&mut self, addr: usize) -> &mut Usage {
+        if addr >= self.usage.len() {
+            self.usage.resize(addr + 1, Usage::default());
+        }
+        &mut self.usage[addr]
+    }
+
+    /// Notes `instruction` at `ip` with operand addresses `addrs` (`None`
+    /// for immediates), which left the process at `next`.
+    pub fn record(
+        &mut self,
+        ip: usize,
+        instruction: Instruction,
+        addrs: &[Option<usize>],
+        next: usize,
+    ) {
+        self.mark(ip).opcode = true;
+        for addr in ip + 1..ip + instruction.size() {
+            self.mark(addr).operand = true;
+        }
+        let jumped = next != ip + instruction.size();
+        for (i, addr) in addrs.iter().enumerate() {
+            let addr = match addr {
+                Some(addr) => *addr,
+                None => continue,
+            };
+            if Some(i) == instruction.dest_index() {
+                self.mark(addr).written = true;
+            } else {
+                let is_target = i == 1
+                    && matches!(
+                        instruction,
+                        Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..)
+                    );
+                if jumped || !is_target {
+                    self.mark(addr).read = true;
+                }
+            }
+        }
+    }
+
+    /// Adds the runs of `other` to this map.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (addr, &usage) in other.usage.iter().enumerate() {
+            self.mark(addr).merge(usage);
+        }
+        self.runs += other.runs;
+    }
+
+    /// The disassembly of `code` with the usage of every word beside it,
+    /// after a summary of how much of it was executed, used as data or
+    /// never touched.
+    pub fn report(&self, code: &[i64]) -> String {
+        let len = code.len().max(self.usage.len());
+        let usage: Vec<_> = (0..len).map(|addr| self.get(addr)).collect();
+        let executed = usage.iter().filter(|u| u.is_executed()).count();
+        let untouched = usage.iter().filter(|u| u.is_untouched()).count();
+        let mut out = format!(
+            "; {} run(s) over {} addresses\n\
+             ; executed: {}\n\
+             ; data only: {}\n\
+             ; untouched: {}\n\
+             ; x opcode, o operand, r read, w written, - untouched\n",
+            self.runs,
+            len,
+            executed,
+            len - executed - untouched,
+            untouched
+        );
+        if len > code.len() {
+            out += &format!(
+                "; {} addresses beyond the program used as data\n",
+                usage[code.len()..]
+                    .iter()
+                    .filter(|u| !u.is_untouched())
+                    .count()
+            );
+        }
+        out += ";\n";
+        out += &disassemble(code).annotate(|entry| {
+            (entry.addr..entry.addr + entry.len())
+                .map(|addr| self.get(addr).tag())
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::Process;
+
+    #[test]
+    fn test_coverage() {
+        // Outputs 1 for a zero input and 2 otherwise.
+        let data = "3,13,1005,13,10,104,1,1105,1,12,104,2,99,0";
+        let code = Process::new(data).code;
+        let run = |input| {
+            let mut process = Process::new(data);
+            process.input(input);
+            process.enable_coverage();
+            process.execute().unwrap();
+            process.take_coverage().unwrap()
+        };
+        let zero = run(0);
+        assert!(zero.get(0).opcode);
+        assert!(zero.get(1).operand);
+        assert!(zero.get(13).written && zero.get(13).read);
+        assert!(zero.get(10).is_untouched());
+        assert!(zero.get(5).opcode);
+
+        assert!(zero.get(12).opcode);
+
+        let seven = run(7);
+        assert!(seven.get(5).is_untouched());
+        let mut all = Coverage::default();
+        all.merge(&seven);
+        all.merge(&zero);
+        assert_eq!(all.runs, 2);
+        assert!(all.get(5).opcode && all.get(10).opcode);
+        let report = all.report(&code);
+        assert!(report.contains("; executed: 13\n"));
+        assert!(report.contains("; data only: 1\n"));
+        assert!(report.contains("; untouched: 0\n"));
+        assert!(report.contains("in [13]"));
+        assert!(report.contains("; x o o\n"));
+        assert!(report
+            .lines()
+            .any(|line| line.starts_with("0013") && line.ends_with("; rw")));
+    }
+}
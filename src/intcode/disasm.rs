@@ -0,0 +1,242 @@
+use super::{Instruction, Parameter, ParameterMode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+const DATA_PER_LINE: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub addr: usize,
+    pub words: Vec<i64>,
+    /// `None` for words emitted as a `.data` directive.
+    pub instruction: Option<Instruction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    pub entries: Vec<Entry>,
+    /// Addresses that are the immediate target of some jump.
+    pub labels: BTreeSet<usize>,
+    /// Addresses of jumps whose target is read from memory and so can't be followed.
+    pub unresolved: BTreeSet<usize>,
+    /// Reached addresses that don't hold a valid instruction, typically
+    /// because the program patches itself before getting there.
+    pub undecodable: BTreeSet<usize>,
+}
+
+pub fn label(addr: usize) -> String {
+    format!("L{}", addr)
+}
+
+/// Disassembles `code` by following fall-through and immediate jump targets
+/// from address 0. Everything not reached that way is listed as `.data`.
+pub fn disassemble(code: &[i64]) -> Listing {
+    let mut claimed = vec![false; code.len()];
+    let mut starts = BTreeMap::new();
+    let mut labels = BTreeSet::new();
+    let mut unresolved = BTreeSet::new();
+    let mut undecodable = BTreeSet::new();
+    let mut to_visit = vec![0];
+    while let Some(ip) = to_visit.pop() {
+        if ip >= code.len() || claimed[ip] {
+            continue;
+        }
+        let instruction = match Instruction::decode(code, ip) {
+            Ok(instruction) => instruction,
+            Err(_) => {
+                undecodable.insert(ip);
+                continue;
+            }
+        };
+        let next = ip + instruction.size();
+        if next > code.len() || claimed[ip..next].iter().any(|&c| c) {
+            undecodable.insert(ip);
+            continue;
+        }
+        claimed[ip..next].iter_mut().for_each(|c| *c = true);
+        starts.insert(ip, instruction);
+        match instruction {
+            Instruction::Halt => (),
+            Instruction::JumpIfTrue(cond, target) | Instruction::JumpIfFalse(cond, target) => {
+                let jump_on = matches!(instruction, Instruction::JumpIfTrue(..));
+                let constant = cond.mode == ParameterMode::Immediate;
+                let always = constant && (cond.value != 0) == jump_on;
+                let never = constant && !always;
+                if !never {
+                    if target.mode == ParameterMode::Immediate && target.value >= 0 {
+                        labels.insert(target.value as usize);
+                        to_visit.push(target.value as usize);
+                    } else {
+                        unresolved.insert(ip);
+                    }
+                }
+                if !always {
+                    to_visit.push(next);
+                }
+            }
+            _ => to_visit.push(next),
+        }
+    }
+
+    let mut entries = vec![];
+    let mut addr = 0;
+    while addr < code.len() {
+        if let Some(&instruction) = starts.get(&addr) {
+            let next = addr + instruction.size();
+            entries.push(Entry {
+                addr,
+                words: code[addr..next].to_vec(),
+                instruction: Some(instruction),
+            });
+            addr = next;
+        } else {
+            let mut end = addr + 1;
+            while end < code.len()
+                && end - addr < DATA_PER_LINE
+                && !claimed[end]
+                && !labels.contains(&end)
+                && !undecodable.contains(&end)
+            {
+                end += 1;
+            }
+            entries.push(Entry {
+                addr,
+                words: code[addr..end].to_vec(),
+                instruction: None,
+            });
+            addr = end;
+        }
+    }
+    Listing {
+        entries,
+        labels,
+        unresolved,
+        undecodable,
+    }
+}
+
+fn format_param(param: Parameter, is_target: bool) -> String {
+    if is_target && param.mode == ParameterMode::Immediate && param.value >= 0 {
+        label(param.value as usize)
+    } else {
+        param.to_string()
+    }
+}
+
+impl Entry {
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// The assembly text of the entry, without address or raw words.
+    pub fn text(&self) -> String {
+        match self.instruction {
+            Some(instruction) => {
+                let is_jump = matches!(
+                    instruction,
+                    Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..)
+                );
+                let params = instruction.params();
+                let operands = params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &param)| format_param(param, is_jump && i == 1))
+                    .collect::<Vec<_>>();
+                if operands.is_empty() {
+                    instruction.mnemonic().to_owned()
+                } else {
+                    format!("{} {}", instruction.mnemonic(), operands.join(", "))
+                }
+            }
+            None => {
+                let values = self.words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+                format!(".data {}", values.join(", "))
+            }
+        }
+    }
+}
+
+impl Listing {
+    /// Renders the listing, appending `annotation(entry)` as a trailing
+    /// comment on every line where it is non-empty.
+    pub fn annotate<F: FnMut(&Entry) -> String>(&self, mut annotation: F) -> String {
+        let mut out = String::new();
+        let mut in_code = true;
+        for entry in &self.entries {
+            let is_code = entry.instruction.is_some();
+            if self.undecodable.contains(&entry.addr) {
+                out.push_str("; reached but not decodable: patched at runtime or data\n");
+            } else if !is_code && in_code {
+                let decodes = entry
+                    .words
+                    .first()
+                    .is_some_and(|&op| Instruction::decode(&[op], 0).is_ok());
+                if !self.unresolved.is_empty() && decodes {
+                    out.push_str("; unreached: code behind a computed jump or data\n");
+                } else {
+                    out.push_str("; unreached: data\n");
+                }
+            }
+            in_code = is_code;
+            if self.labels.contains(&entry.addr) {
+                out.push_str(&format!("{}:\n", label(entry.addr)));
+            }
+            let words = if is_code {
+                entry
+                    .words
+                    .iter()
+                    .map(|w| w.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            } else {
+                String::new()
+            };
+            let mut line = format!("{:04}  {:<24}{}", entry.addr, words, entry.text());
+            let comment = annotation(entry);
+            if !comment.is_empty() {
+                line = format!("{:<64}; {}", line, comment);
+            } else if self.unresolved.contains(&entry.addr) {
+                line = format!("{:<64}; computed target", line);
+            }
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.annotate(|_| String::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let code = [3, 12, 1005, 12, 10, 104, 0, 1105, 1, 11, 99, 99, 0];
+        let listing = disassemble(&code);
+        assert_eq!(
+            listing.to_string(),
+            "\
+0000  3,12                    in [12]
+0002  1005,12,10              jnz [12], L10
+0005  104,0                   out #0
+0007  1105,1,11               jnz #1, L11
+L10:
+0010  99                      halt
+L11:
+0011  99                      halt
+; unreached: data
+0012                          .data 0
+"
+        );
+    }
+}
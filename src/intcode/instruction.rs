@@ -0,0 +1,282 @@
+use super::IntcodeError;
+use std::fmt;
+use std::ops::Deref;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParameterMode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl ParameterMode {
+    pub fn code(self) -> i64 {
+        match self {
+            ParameterMode::Position => 0,
+            ParameterMode::Immediate => 1,
+            ParameterMode::Relative => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Parameter {
+    pub mode: ParameterMode,
+    pub value: i64,
+}
+
+impl fmt::Display for Parameter {
+    /// Formats as `[12]` for position, `#5` for immediate and `[rb+3]` for relative mode.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            ParameterMode::Position => write!(f, "[{}]", self.value),
+            ParameterMode::Immediate => write!(f, "#{}", self.value),
+            ParameterMode::Relative if self.value < 0 => write!(f, "[rb{}]", self.value),
+            ParameterMode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+/// The operands of an instruction, held inline so getting them doesn't
+/// allocate. Derefs to a slice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Params {
+    params: [Parameter; 3],
+    len: usize,
+}
+
+impl Params {
+    fn new(given: &[Parameter]) -> Self {
+        let mut params = [Parameter {
+            mode: ParameterMode::Position,
+            value: 0,
+        }; 3];
+        params[..given.len()].copy_from_slice(given);
+        Self {
+            params,
+            len: given.len(),
+        }
+    }
+}
+
+impl Deref for Params {
+    type Target = [Parameter];
+
+    fn deref(&self) -> &[Parameter] {
+        &self.params[..self.len]
+    }
+}
+
+impl IntoIterator for Params {
+    type Item = Parameter;
+    type IntoIter = std::iter::Take<std::array::IntoIter<Parameter, 3>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIterator::into_iter(self.params).take(self.len)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    Add(Parameter, Parameter, Parameter),
+    Mul(Parameter, Parameter, Parameter),
+    In(Parameter),
+    Out(Parameter),
+    JumpIfTrue(Parameter, Parameter),
+    JumpIfFalse(Parameter, Parameter),
+    LessThan(Parameter, Parameter, Parameter),
+    Equals(Parameter, Parameter, Parameter),
+    AdjustRelativeBase(Parameter),
+    Halt,
+}
+
+impl Instruction {
+    /// Decodes the instruction at `ip`. Memory past the end of `code` reads as zero.
+    pub fn decode(code: &[i64], ip: usize) -> Result<Self, IntcodeError> {
+        use Instruction::*;
+        let read = |addr: usize| code.get(addr).copied().unwrap_or(0);
+        let op = read(ip);
+        let param = |n: usize| -> Result<Parameter, IntcodeError> {
+            let mode = match op / 10i64.pow(n as u32 + 1) % 10 {
+                0 => ParameterMode::Position,
+                1 => ParameterMode::Immediate,
+                2 => ParameterMode::Relative,
+                mode => {
+                    return Err(IntcodeError::UnknownMode {
+                        ip,
+                        opcode: op,
+                        mode,
+                    })
+                }
+            };
+            Ok(Parameter {
+                mode,
+                value: read(ip + n),
+            })
+        };
+        let dest = |n: usize| -> Result<Parameter, IntcodeError> {
+            let p = param(n)?;
+            if p.mode == ParameterMode::Immediate {
+                Err(IntcodeError::ImmediateWrite { ip, opcode: op })
+            } else {
+                Ok(p)
+            }
+        };
+        if op < 0 {
+            return Err(IntcodeError::UnknownOpcode { ip, opcode: op });
+        }
+        Ok(match op % 100 {
+            1 => Add(param(1)?, param(2)?, dest(3)?),
+            2 => Mul(param(1)?, param(2)?, dest(3)?),
+            3 => In(dest(1)?),
+            4 => Out(param(1)?),
+            5 => JumpIfTrue(param(1)?, param(2)?),
+            6 => JumpIfFalse(param(1)?, param(2)?),
+            7 => LessThan(param(1)?, param(2)?, dest(3)?),
+            8 => Equals(param(1)?, param(2)?, dest(3)?),
+            9 => AdjustRelativeBase(param(1)?),
+            99 if op == 99 => Halt,
+            99 => {
+                // `halt` has no parameters, so any mode digit is unknown.
+                let mut modes = op / 100;
+                while modes % 10 == 0 {
+                    modes /= 10;
+                }
+                return Err(IntcodeError::UnknownMode {
+                    ip,
+                    opcode: op,
+                    mode: modes % 10,
+                });
+            }
+            _ => return Err(IntcodeError::UnknownOpcode { ip, opcode: op }),
+        })
+    }
+
+    pub fn opcode(&self) -> i64 {
+        use Instruction::*;
+        match self {
+            Add(..) => 1,
+            Mul(..) => 2,
+            In(..) => 3,
+            Out(..) => 4,
+            JumpIfTrue(..) => 5,
+            JumpIfFalse(..) => 6,
+            LessThan(..) => 7,
+            Equals(..) => 8,
+            AdjustRelativeBase(..) => 9,
+            Halt => 99,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        use Instruction::*;
+        match self {
+            Add(..) => "add",
+            Mul(..) => "mul",
+            In(..) => "in",
+            Out(..) => "out",
+            JumpIfTrue(..) => "jnz",
+            JumpIfFalse(..) => "jz",
+            LessThan(..) => "lt",
+            Equals(..) => "eq",
+            AdjustRelativeBase(..) => "arb",
+            Halt => "halt",
+        }
+    }
+
+    /// Builds an instruction from its mnemonic and operands, or `None` if the
+    /// mnemonic is unknown or takes a different number of operands.
+    pub fn from_parts(mnemonic: &str, params: &[Parameter]) -> Option<Self> {
+        use Instruction::*;
+        Some(match (mnemonic, params) {
+            ("add", &[a, b, c]) => Add(a, b, c),
+            ("mul", &[a, b, c]) => Mul(a, b, c),
+            ("in", &[a]) => In(a),
+            ("out", &[a]) => Out(a),
+            ("jnz", &[a, b]) => JumpIfTrue(a, b),
+            ("jz", &[a, b]) => JumpIfFalse(a, b),
+            ("lt", &[a, b, c]) => LessThan(a, b, c),
+            ("eq", &[a, b, c]) => Equals(a, b, c),
+            ("arb", &[a]) => AdjustRelativeBase(a),
+            ("halt", &[]) => Halt,
+            _ => return None,
+        })
+    }
+
+    /// Index into `params()` of the operand the instruction writes to.
+    pub fn dest_index(&self) -> Option<usize> {
+        use Instruction::*;
+        match self {
+            Add(..) | Mul(..) | LessThan(..) | Equals(..) => Some(2),
+            In(..) => Some(0),
+            _ => None,
+        }
+    }
+
+    pub fn params(&self) -> Params {
+        use Instruction::*;
+        match *self {
+            Add(a, b, c) | Mul(a, b, c) | LessThan(a, b, c) | Equals(a, b, c) => {
+                Params::new(&[a, b, c])
+            }
+            JumpIfTrue(a, b) | JumpIfFalse(a, b) => Params::new(&[a, b]),
+            In(a) | Out(a) | AdjustRelativeBase(a) => Params::new(&[a]),
+            Halt => Params::new(&[]),
+        }
+    }
+
+    /// Number of memory cells the instruction occupies, opcode included.
+    pub fn size(&self) -> usize {
+        use Instruction::*;
+        match self {
+            Add(..) | Mul(..) | LessThan(..) | Equals(..) => 4,
+            JumpIfTrue(..) | JumpIfFalse(..) => 3,
+            In(..) | Out(..) | AdjustRelativeBase(..) => 2,
+            Halt => 1,
+        }
+    }
+
+    /// Encodes the instruction back into its memory representation.
+    pub fn encode(&self) -> Vec<i64> {
+        let params = self.params();
+        let op = params.iter().enumerate().fold(self.opcode(), |op, (i, p)| {
+            op + p.mode.code() * 10i64.pow(i as u32 + 2)
+        });
+        std::iter::once(op)
+            .chain(params.iter().map(|p| p.value))
+            .collect()
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        for (i, param) in self.params().iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, param)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let code = [1002, 4, 3, 4, 33];
+        let instruction = Instruction::decode(&code, 0).unwrap();
+        assert_eq!(instruction.to_string(), "mul [4], #3, [4]");
+        assert_eq!(instruction.encode(), &code[..4]);
+        let code = [109, -7, 21201, 3, 5, 1, 99];
+        assert_eq!(
+            Instruction::decode(&code, 2).unwrap().to_string(),
+            "add [rb+3], #5, [rb+1]"
+        );
+        assert_eq!(Instruction::decode(&code, 6), Ok(Instruction::Halt));
+        assert_eq!(
+            Instruction::decode(&code, 7),
+            Err(IntcodeError::UnknownOpcode { ip: 7, opcode: 0 })
+        );
+    }
+}
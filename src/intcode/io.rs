@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Returned by `IntcodeIo::write_output` when nobody can receive output anymore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed;
+
+/// Where a `Process` takes its input from and sends its output to.
+pub trait IntcodeIo {
+    /// The next input value, or `None` if there is none (yet). Returning
+    /// `None` leaves the process at its input instruction, ready to resume.
+    fn read_input(&mut self) -> Option<i64>;
+
+    fn write_output(&mut self, value: i64) -> Result<(), Closed>;
+}
+
+impl<T: IntcodeIo + ?Sized> IntcodeIo for Box<T> {
+    fn read_input(&mut self) -> Option<i64> {
+        (**self).read_input()
+    }
+
+    fn write_output(&mut self, value: i64) -> Result<(), Closed> {
+        (**self).write_output(value)
+    }
+}
+
+impl<T: IntcodeIo + ?Sized> IntcodeIo for &mut T {
+    fn read_input(&mut self) -> Option<i64> {
+        (**self).read_input()
+    }
+
+    fn write_output(&mut self, value: i64) -> Result<(), Closed> {
+        (**self).write_output(value)
+    }
+}
+
+/// In-memory input and output queues.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferIo {
+    pub input: VecDeque<i64>,
+    pub output: VecDeque<i64>,
+}
+
+impl IntcodeIo for BufferIo {
+    fn read_input(&mut self) -> Option<i64> {
+        self.input.pop_front()
+    }
+
+    fn write_output(&mut self, value: i64) -> Result<(), Closed> {
+        self.output.push_back(value);
+        Ok(())
+    }
+}
+
+/// Input from one closure and output to another, e.g. `FnIo::new(|| iter.next(), |n| v.push(n))`.
+pub struct FnIo<I, O> {
+    pub input: I,
+    pub output: O,
+}
+
+impl<I: FnMut() -> Option<i64>, O: FnMut(i64)> FnIo<I, O> {
+    pub fn new(input: I, output: O) -> Self {
+        Self { input, output }
+    }
+}
+
+impl<I: FnMut() -> Option<i64>, O: FnMut(i64)> IntcodeIo for FnIo<I, O> {
+    fn read_input(&mut self) -> Option<i64> {
+        (self.input)()
+    }
+
+    fn write_output(&mut self, value: i64) -> Result<(), Closed> {
+        (self.output)(value);
+        Ok(())
+    }
+}
+
+/// Input and output over `std::sync::mpsc` channels.
+#[derive(Debug)]
+pub struct ChannelIo {
+    pub input: Receiver<i64>,
+    pub output: Sender<i64>,
+    /// Wait for input instead of reporting that there is none. Input only
+    /// runs out once every sender is gone.
+    pub blocking: bool,
+}
+
+impl ChannelIo {
+    /// Creates the I/O along with the sender feeding its input and the
+    /// receiver collecting its output.
+    pub fn new() -> (Self, Sender<i64>, Receiver<i64>) {
+        let (input_tx, input) = channel();
+        let (output, output_rx) = channel();
+        let io = Self {
+            input,
+            output,
+            blocking: false,
+        };
+        (io, input_tx, output_rx)
+    }
+
+    /// Like `new`, but reading input blocks until a value arrives.
+    pub fn blocking() -> (Self, Sender<i64>, Receiver<i64>) {
+        let (io, input_tx, output_rx) = Self::new();
+        let io = Self {
+            blocking: true,
+            ..io
+        };
+        (io, input_tx, output_rx)
+    }
+}
+
+impl IntcodeIo for ChannelIo {
+    fn read_input(&mut self) -> Option<i64> {
+        if self.blocking {
+            self.input.recv().ok()
+        } else {
+            self.input.try_recv().ok()
+        }
+    }
+
+    fn write_output(&mut self, value: i64) -> Result<(), Closed> {
+        self.output.send(value).map_err(|_| Closed)
+    }
+}
+
+/// Reads integers separated by commas or whitespace from stdin and prints
+/// one output value per line to stdout.
+#[derive(Debug, Default)]
+pub struct StdIo {
+    pending: VecDeque<i64>,
+}
+
+impl IntcodeIo for StdIo {
+    fn read_input(&mut self) -> Option<i64> {
+        let stdin = io::stdin();
+        while self.pending.is_empty() {
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            self.pending.extend(
+                line.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter_map(|n| n.parse::<i64>().ok()),
+            );
+        }
+        self.pending.pop_front()
+    }
+
+    fn write_output(&mut self, value: i64) -> Result<(), Closed> {
+        writeln!(io::stdout(), "{}", value).map_err(|_| Closed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{Process, Status};
+
+    #[test]
+    fn test_fn_io() {
+        let mut inputs = vec![3, 4].into_iter();
+        let mut outputs = vec![];
+        let code = vec![3, 11, 3, 12, 2, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+        let mut process = Process::with_code(
+            code,
+            FnIo::new(|| inputs.next(), |value| outputs.push(value)),
+        );
+        process.execute().unwrap();
+        drop(process);
+        assert_eq!(outputs, vec![12]);
+    }
+
+    #[test]
+    fn test_channel_io() {
+        let (io, tx, rx) = ChannelIo::new();
+        let mut process = Process::new("3,0,4,0,99").with_io(io);
+        assert_eq!(process.run(), Ok(Status::AwaitingInput));
+        tx.send(5).unwrap();
+        process.execute().unwrap();
+        assert_eq!(rx.try_recv(), Ok(5));
+    }
+}
@@ -0,0 +1,282 @@
+use super::{BufferIo, Instruction, IntcodeError, IntcodeIo, Process};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// How many states `LoopDetector` remembers before starting over, which
+/// bounds its memory use. Loops longer than this go undetected.
+const MAX_SEEN: usize = 1 << 20;
+
+/// Bare machine state, without I/O.
+#[derive(Clone)]
+struct State {
+    code: Arc<Vec<i64>>,
+    ip: usize,
+    relative_base: i64,
+}
+
+impl State {
+    fn of<IO>(process: &Process<IO>) -> Self {
+        Self {
+            code: Arc::clone(&process.code),
+            ip: process.ip,
+            relative_base: process.relative_base,
+        }
+    }
+
+    fn matches<IO>(&self, process: &Process<IO>) -> bool {
+        self.ip == process.ip
+            && self.relative_base == process.relative_base
+            && same_memory(&self.code, &process.code)
+    }
+
+    fn to_process(&self) -> Process {
+        Process {
+            code: Arc::clone(&self.code),
+            ip: self.ip,
+            relative_base: self.relative_base,
+            ..Process::with_code(Vec::new(), BufferIo::default())
+        }
+    }
+}
+
+/// Compares memory, treating words past either end as zero.
+fn same_memory(a: &[i64], b: &[i64]) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    long[..short.len()] == short[..] && long[short.len()..].iter().all(|&n| n == 0)
+}
+
+fn mix(addr: usize, value: i64) -> u64 {
+    if value == 0 {
+        return 0;
+    }
+    // splitmix64 finaliser
+    let mut z = (addr as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ value as u64;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Memory hash of `process`, see `step_hashed`.
+fn hash<IO>(process: &Process<IO>) -> u64 {
+    process
+        .code
+        .iter()
+        .enumerate()
+        .fold(0, |hash, (addr, &value)| {
+            hash.wrapping_add(mix(addr, value))
+        })
+}
+
+/// Steps `process`, updating `hash` for the word it writes. Returns the
+/// instruction executed.
+fn step_hashed<IO: IntcodeIo>(
+    process: &mut Process<IO>,
+    hash: &mut u64,
+) -> Result<Instruction, IntcodeError> {
+    let instruction = Instruction::decode(&process.code, process.ip)?;
+    let dest = instruction
+        .dest_index()
+        .and_then(|i| process.param_addr(instruction.params()[i]).ok());
+    let old = dest.map(|addr| process.read(addr));
+    process.step()?;
+    if let (Some(addr), Some(old)) = (dest, old) {
+        *hash = hash
+            .wrapping_sub(mix(addr, old))
+            .wrapping_add(mix(addr, process.read(addr)));
+    }
+    Ok(instruction)
+}
+
+struct Candidate {
+    state: State,
+    at: u64,
+    cycle_len: u64,
+}
+
+/// Spots states repeating between two input events. Memory is hashed
+/// incrementally, so each step costs a hash map lookup. A repeated hash is
+/// only a candidate: it becomes an error once the exact state is seen again
+/// one cycle later, which proves the program will never get out.
+struct LoopDetector {
+    base: State,
+    base_hash: u64,
+    hash: u64,
+    steps: u64,
+    seen: HashMap<(usize, i64, u64), u64>,
+    candidate: Option<Candidate>,
+}
+
+impl LoopDetector {
+    fn new<IO>(process: &Process<IO>) -> Self {
+        Self {
+            base: State::of(process),
+            base_hash: hash(process),
+            hash: hash(process),
+            steps: 0,
+            seen: HashMap::new(),
+            candidate: None,
+        }
+    }
+
+    fn step<IO: IntcodeIo>(&mut self, process: &mut Process<IO>) -> Result<(), IntcodeError> {
+        let instruction = step_hashed(process, &mut self.hash)?;
+        if let Instruction::In(_) = instruction {
+            self.base = State::of(process);
+            self.base_hash = self.hash;
+            self.steps = 0;
+            self.seen.clear();
+            self.candidate = None;
+            return Ok(());
+        }
+        self.steps += 1;
+
+        if let Some(candidate) = &self.candidate {
+            if self.steps == candidate.at + candidate.cycle_len {
+                if candidate.state.matches(process) {
+                    return Err(self.diagnose(process, candidate.cycle_len));
+                }
+                self.candidate = None;
+            }
+        }
+        if self.seen.len() >= MAX_SEEN {
+            self.seen.clear();
+        }
+        let key = (process.ip, process.relative_base, self.hash);
+        if let Some(previous) = self.seen.insert(key, self.steps) {
+            if self.candidate.is_none() {
+                self.candidate = Some(Candidate {
+                    state: State::of(process),
+                    at: self.steps,
+                    cycle_len: self.steps - previous,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Replays from the last input event to find where the cycle starts:
+    /// two copies `cycle_len` steps apart first meet at the loop entry.
+    /// Memory is only compared where the hashes agree.
+    fn diagnose<IO>(&self, process: &Process<IO>, cycle_len: u64) -> IntcodeError {
+        let mut slow = self.base.to_process();
+        let mut fast = self.base.to_process();
+        let (mut slow_hash, mut fast_hash) = (self.base_hash, self.base_hash);
+        for _ in 0..cycle_len {
+            step_hashed(&mut fast, &mut fast_hash).unwrap();
+        }
+        while !(slow.ip == fast.ip
+            && slow.relative_base == fast.relative_base
+            && slow_hash == fast_hash
+            && same_memory(&slow.code, &fast.code))
+        {
+            step_hashed(&mut slow, &mut slow_hash).unwrap();
+            step_hashed(&mut fast, &mut fast_hash).unwrap();
+        }
+        IntcodeError::InfiniteLoop {
+            ip: process.ip,
+            opcode: process.read(process.ip),
+            entry: slow.ip,
+            cycle_len,
+        }
+    }
+}
+
+impl<IO: IntcodeIo> Process<IO> {
+    /// Like `execute`, but fails with `IntcodeError::StepLimit` instead of
+    /// running more than `max_steps` instructions.
+    pub fn execute_with_limit(&mut self, max_steps: u64) -> Result<(), IntcodeError> {
+        self.execute_guarded(Some(max_steps), false)
+    }
+
+    /// Like `execute`, optionally with a step limit, failing with
+    /// `IntcodeError::InfiniteLoop` as soon as the program provably loops
+    /// forever without asking for input.
+    pub fn execute_detecting_loops(&mut self, max_steps: Option<u64>) -> Result<(), IntcodeError> {
+        self.execute_guarded(max_steps, true)
+    }
+
+    fn execute_guarded(
+        &mut self,
+        max_steps: Option<u64>,
+        detect_loops: bool,
+    ) -> Result<(), IntcodeError> {
+        let mut detector = if detect_loops {
+            Some(LoopDetector::new(self))
+        } else {
+            None
+        };
+        let mut steps = 0;
+        while !self.is_finished() {
+            if max_steps.is_some_and(|max| steps >= max) {
+                return Err(IntcodeError::StepLimit {
+                    ip: self.ip,
+                    opcode: self.read(self.ip),
+                    steps,
+                });
+            }
+            match &mut detector {
+                Some(detector) => detector.step(self)?,
+                None => self.step()?,
+            }
+            steps += 1;
+        }
+        self.record_halt();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    #[test]
+    fn test_step_limit() {
+        let mut process = Process::new("1105,1,0");
+        assert_eq!(
+            process.execute_with_limit(10),
+            Err(IntcodeError::StepLimit {
+                ip: 0,
+                opcode: 1105,
+                steps: 10
+            })
+        );
+        let mut process = Process::new("1101,1,1,0,99");
+        assert_eq!(process.execute_with_limit(10), Ok(()));
+    }
+
+    #[test]
+    fn test_detect_loop() {
+        let source = "
+                    in [n]
+            count:  add [n], #-1, [n]
+                    jnz [n], count
+            spin:   add [x], #1, [x]
+                    eq [x], #3, [y]
+                    jz [y], skip
+                    add #0, #0, [x]
+            skip:   jz #0, spin
+            n:      .data 0
+            x:      .data 0
+            y:      .data 0
+        ";
+        let code = assemble(source).unwrap();
+        let mut process = Process::with_code(code, BufferIo::default());
+        process.input(1000);
+        match process.execute_detecting_loops(Some(1_000_000)) {
+            Err(IntcodeError::InfiniteLoop {
+                entry, cycle_len, ..
+            }) => {
+                // The first pass through the loop still has `y` unset; the state
+                // first repeats right after `eq` sets it.
+                assert_eq!(entry, 17);
+                assert_eq!(cycle_len, 13);
+            }
+            other => panic!("expected a loop, got {:?}", other),
+        }
+
+        let mut process = Process::new("3,0,4,0,99");
+        process.input(1);
+        assert_eq!(process.execute_detecting_loops(None), Ok(()));
+    }
+}
@@ -0,0 +1,372 @@
+//! Several processes wired together by named edges.
+//!
+//! ```text
+//! let mut network = Network::builder()
+//!     .node("a", a)
+//!     .node("b", b)
+//!     .edge("a->b", "a", "b")
+//!     .edge("b->a", "b", "a")
+//!     .tap("out", "b")
+//!     .build()?;
+//! network.input("a", 0);
+//! let status = network.run()?;
+//! ```
+
+use super::{IntcodeError, Process, Status};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkError {
+    UnknownNode(String),
+    DuplicateName(String),
+    Process { node: String, error: IntcodeError },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::UnknownNode(name) => write!(f, "unknown node `{}`", name),
+            NetworkError::DuplicateName(name) => write!(f, "`{}` is defined twice", name),
+            NetworkError::Process { node, error } => write!(f, "node `{}` {}", node, error),
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+/// Why `Network::run` stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkStatus {
+    Halted,
+    /// Every node still running waits for input that nobody will send.
+    Deadlocked {
+        waiting: Vec<String>,
+    },
+    /// Some nodes can't hand on output because an edge is full; the rest
+    /// wait for input.
+    OutputBlocked {
+        blocked: Vec<String>,
+        waiting: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeState {
+    Running,
+    WaitingInput,
+    BlockedOutput(i64),
+    Halted,
+}
+
+struct Node {
+    name: String,
+    process: Process,
+    state: NodeState,
+    incoming: Vec<usize>,
+    outgoing: Vec<usize>,
+}
+
+struct Edge {
+    name: String,
+    capacity: Option<usize>,
+    queue: VecDeque<i64>,
+}
+
+impl Edge {
+    fn is_full(&self) -> bool {
+        self.capacity
+            .is_some_and(|capacity| self.queue.len() >= capacity)
+    }
+}
+
+#[derive(Default)]
+pub struct NetworkBuilder {
+    nodes: Vec<(String, Process)>,
+    edges: Vec<(String, String, Option<String>, Option<usize>)>,
+}
+
+impl NetworkBuilder {
+    pub fn node(mut self, name: &str, process: Process) -> Self {
+        self.nodes.push((name.to_owned(), process));
+        self
+    }
+
+    /// Connects the output of `from` to the input of `to`. A node with
+    /// several outgoing edges sends every value down each of them; a node
+    /// with several incoming edges reads them in the order they were added.
+    pub fn edge(self, name: &str, from: &str, to: &str) -> Self {
+        self.add_edge(name, from, Some(to), None)
+    }
+
+    /// Like `edge`, but holding at most `capacity` values. A node whose
+    /// output finds the edge full blocks until there is room again.
+    pub fn bounded_edge(self, name: &str, from: &str, to: &str, capacity: usize) -> Self {
+        self.add_edge(name, from, Some(to), Some(capacity))
+    }
+
+    /// An edge leading out of the network, read with `Network::output`.
+    pub fn tap(self, name: &str, from: &str) -> Self {
+        self.add_edge(name, from, None, None)
+    }
+
+    fn add_edge(
+        mut self,
+        name: &str,
+        from: &str,
+        to: Option<&str>,
+        capacity: Option<usize>,
+    ) -> Self {
+        self.edges.push((
+            name.to_owned(),
+            from.to_owned(),
+            to.map(str::to_owned),
+            capacity,
+        ));
+        self
+    }
+
+    pub fn build(self) -> Result<Network, NetworkError> {
+        let mut nodes: Vec<Node> = vec![];
+        for (name, process) in self.nodes {
+            if nodes.iter().any(|node| node.name == name) {
+                return Err(NetworkError::DuplicateName(name));
+            }
+            nodes.push(Node {
+                name,
+                process,
+                state: NodeState::Running,
+                incoming: vec![],
+                outgoing: vec![],
+            });
+        }
+        let mut edges: Vec<Edge> = vec![];
+        for (name, from, to, capacity) in self.edges {
+            if edges.iter().any(|edge| edge.name == name) {
+                return Err(NetworkError::DuplicateName(name));
+            }
+            let index = |name: &str| {
+                nodes
+                    .iter()
+                    .position(|node| node.name == name)
+                    .ok_or_else(|| NetworkError::UnknownNode(name.to_owned()))
+            };
+            let from = index(&from)?;
+            if let Some(to) = to {
+                let to = index(&to)?;
+                nodes[to].incoming.push(edges.len());
+            }
+            nodes[from].outgoing.push(edges.len());
+            edges.push(Edge {
+                name,
+                capacity,
+                queue: VecDeque::new(),
+            });
+        }
+        Ok(Network { nodes, edges })
+    }
+}
+
+pub struct Network {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+impl Network {
+    pub fn builder() -> NetworkBuilder {
+        NetworkBuilder::default()
+    }
+
+    fn node_mut(&mut self, name: &str) -> Option<&mut Node> {
+        self.nodes.iter_mut().find(|node| node.name == name)
+    }
+
+    /// Queues input for a node directly, bypassing edges.
+    pub fn input(&mut self, node: &str, value: i64) -> Result<(), NetworkError> {
+        let node = self
+            .node_mut(node)
+            .ok_or_else(|| NetworkError::UnknownNode(node.to_owned()))?;
+        node.process.input(value);
+        Ok(())
+    }
+
+    /// Takes the values that reached the end of an edge or tap so far.
+    pub fn output(&mut self, edge: &str) -> Option<Vec<i64>> {
+        self.edges
+            .iter_mut()
+            .find(|e| e.name == edge)
+            .map(|edge| edge.queue.drain(..).collect())
+    }
+
+    pub fn process(&self, node: &str) -> Option<&Process> {
+        self.nodes
+            .iter()
+            .find(|n| n.name == node)
+            .map(|node| &node.process)
+    }
+
+    /// Sends `value` down every outgoing edge of node `i`, or nowhere if any
+    /// of them is full. Output of a node without edges stays in its process.
+    fn deliver(&mut self, i: usize, value: i64) -> bool {
+        let node = &mut self.nodes[i];
+        let edges = &mut self.edges;
+        if node.outgoing.is_empty() {
+            node.process.io.output.push_back(value);
+            return true;
+        }
+        if node.outgoing.iter().any(|&e| edges[e].is_full()) {
+            return false;
+        }
+        for &e in &node.outgoing {
+            edges[e].queue.push_back(value);
+        }
+        true
+    }
+
+    /// Lets node `i` run until its next I/O event. Returns whether anything changed.
+    fn slice(&mut self, i: usize) -> Result<bool, NetworkError> {
+        match self.nodes[i].state {
+            NodeState::Halted => Ok(false),
+            NodeState::BlockedOutput(value) => {
+                let delivered = self.deliver(i, value);
+                if delivered {
+                    self.nodes[i].state = NodeState::Running;
+                }
+                Ok(delivered)
+            }
+            NodeState::Running | NodeState::WaitingInput => {
+                let node = &mut self.nodes[i];
+                let status = node.process.run().map_err(|error| NetworkError::Process {
+                    node: node.name.clone(),
+                    error,
+                })?;
+                let was_waiting = node.state == NodeState::WaitingInput;
+                match status {
+                    Status::Output(value) => {
+                        node.state = NodeState::Running;
+                        if !self.deliver(i, value) {
+                            self.nodes[i].state = NodeState::BlockedOutput(value);
+                        }
+                        Ok(true)
+                    }
+                    Status::Halted => {
+                        node.state = NodeState::Halted;
+                        Ok(true)
+                    }
+                    Status::AwaitingInput => {
+                        let incoming = node.incoming.iter();
+                        let edges = &mut self.edges;
+                        let value = incoming.filter_map(|&e| edges[e].queue.pop_front()).next();
+                        match value {
+                            Some(value) => {
+                                node.process.input(value);
+                                node.state = NodeState::Running;
+                                Ok(true)
+                            }
+                            None => {
+                                node.state = NodeState::WaitingInput;
+                                Ok(!was_waiting)
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs the nodes round-robin, each until its next I/O event, until no
+    /// node can make progress.
+    pub fn run(&mut self) -> Result<NetworkStatus, NetworkError> {
+        loop {
+            let mut progress = false;
+            for i in 0..self.nodes.len() {
+                progress |= self.slice(i)?;
+            }
+            if !progress {
+                break;
+            }
+        }
+        let names = |pred: fn(NodeState) -> bool| -> Vec<String> {
+            self.nodes
+                .iter()
+                .filter(|node| pred(node.state))
+                .map(|node| node.name.clone())
+                .collect()
+        };
+        let waiting = names(|state| state == NodeState::WaitingInput);
+        let blocked = names(|state| matches!(state, NodeState::BlockedOutput(_)));
+        Ok(if !blocked.is_empty() {
+            NetworkStatus::OutputBlocked { blocked, waiting }
+        } else if !waiting.is_empty() {
+            NetworkStatus::Deadlocked { waiting }
+        } else {
+            NetworkStatus::Halted
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feedback_ring() {
+        let data =
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+        let names = ["A", "B", "C", "D", "E"];
+        let mut builder = Network::builder();
+        for (name, phase) in names.iter().zip(vec![9, 8, 7, 6, 5]) {
+            let mut process = Process::new(data);
+            process.input(phase);
+            builder = builder.node(name, process);
+        }
+        for (from, to) in names.iter().zip(names.iter().cycle().skip(1)) {
+            builder = builder.edge(&format!("{}{}", from, to), from, to);
+        }
+        let mut network = builder.tap("thrusters", "E").build().unwrap();
+        network.input("A", 0).unwrap();
+        assert_eq!(network.run(), Ok(NetworkStatus::Halted));
+        assert_eq!(
+            network.output("thrusters").unwrap().last(),
+            Some(&139629729)
+        );
+    }
+
+    #[test]
+    fn test_stuck_nodes() {
+        let echo = "3,0,4,0,1105,1,0";
+        let mut network = Network::builder()
+            .node("a", Process::new(echo))
+            .node("b", Process::new(echo))
+            .edge("ab", "a", "b")
+            .edge("ba", "b", "a")
+            .build()
+            .unwrap();
+        assert_eq!(
+            network.run(),
+            Ok(NetworkStatus::Deadlocked {
+                waiting: vec!["a".to_owned(), "b".to_owned()]
+            })
+        );
+
+        let mut network = Network::builder()
+            .node("source", Process::new("104,1,1105,1,0"))
+            .node("sink", Process::new("99"))
+            .bounded_edge("pipe", "source", "sink", 2)
+            .build()
+            .unwrap();
+        assert_eq!(
+            network.run(),
+            Ok(NetworkStatus::OutputBlocked {
+                blocked: vec!["source".to_owned()],
+                waiting: vec![]
+            })
+        );
+        assert_eq!(network.output("pipe"), Some(vec![1, 1]));
+        assert_eq!(
+            Network::builder().edge("e", "x", "y").build().err(),
+            Some(NetworkError::UnknownNode("x".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use super::trace::MemoryWrite;
+use super::Instruction;
+
+/// What a patched address was last executed as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeRole {
+    Opcode,
+    Operand,
+}
+
+/// A write landing on an address that had been executed before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Patch {
+    /// Instructions executed before the patching one, since logging began.
+    pub step: u64,
+    /// The instruction doing the write.
+    pub ip: usize,
+    pub write: MemoryWrite,
+    pub role: CodeRole,
+}
+
+/// Tracks which addresses were executed and logs every later write to them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchLog {
+    pub events: Vec<Patch>,
+    executed: Vec<Option<CodeRole>>,
+    steps: u64,
+}
+
+impl PatchLog {
+    /// What `addr` was executed as, if at all.
+    pub fn role(&self, addr: usize) -> Option<CodeRole> {
+        self.executed.get(addr).copied().flatten()
+    }
+
+    /// Notes `instruction` at `ip`, along with its write if it made one.
+    pub fn record(&mut self, ip: usize, instruction: Instruction, write: Option<MemoryWrite>) {
+        let end = ip + instruction.size();
+        if end > self.executed.len() {
+            self.executed.resize(end, None);
+        }
+        self.executed[ip] = Some(CodeRole::Opcode);
+        for role in &mut self.executed[ip + 1..end] {
+            if *role != Some(CodeRole::Opcode) {
+                *role = Some(CodeRole::Operand);
+            }
+        }
+        if let Some(write) = write {
+            if let Some(role) = self.role(write.addr) {
+                self.events.push(Patch {
+                    step: self.steps,
+                    ip,
+                    write,
+                    role,
+                });
+            }
+        }
+        self.steps += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::Process;
+
+    #[test]
+    fn test_patch_log() {
+        // Writes the operand of the `out` at 4 before it first runs, then
+        // rewrites an operand of the `add` at 0 and turns it into a `halt`.
+        let data = "1101,7,0,5,104,0,1101,2,0,1,1101,99,0,0,1105,1,0";
+        let mut process = Process::new(data);
+        process.enable_patch_log();
+        process.execute().unwrap();
+        assert_eq!(process.output(), Some(7));
+        let log = process.take_patch_log().unwrap();
+        assert_eq!(
+            log.events,
+            vec![
+                Patch {
+                    step: 2,
+                    ip: 6,
+                    write: MemoryWrite {
+                        addr: 1,
+                        old: 7,
+                        new: 2
+                    },
+                    role: CodeRole::Operand,
+                },
+                Patch {
+                    step: 3,
+                    ip: 10,
+                    write: MemoryWrite {
+                        addr: 0,
+                        old: 1101,
+                        new: 99
+                    },
+                    role: CodeRole::Opcode,
+                },
+            ]
+        );
+        assert_eq!(log.role(5), Some(CodeRole::Operand));
+        assert_eq!(log.role(17), None);
+    }
+}
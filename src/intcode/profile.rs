@@ -0,0 +1,234 @@
+//! Execution counts per address, opcode, block and jump.
+//!
+//! Blocks are the straight runs of instructions between jumps as they were
+//! executed, keyed by the address they were entered at. For the flamegraph
+//! a function is anything that starts with `arb #n` for a positive `n` and
+//! returns with a negative `arb`, the calling convention of the compiled
+//! puzzle programs.
+
+use super::disasm::{disassemble, label};
+use super::{Instruction, ParameterMode};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+/// How many of the hottest blocks and back-edges `Profile::report` lists.
+const TOP: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Block {
+    /// How often control entered the block.
+    pub entries: u64,
+    /// Instructions executed inside it.
+    pub instructions: u64,
+    /// The address after the furthest instruction executed in it.
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub total: u64,
+    /// Executions per instruction address.
+    pub hits: BTreeMap<usize, u64>,
+    /// Executions per opcode, by mnemonic.
+    pub opcodes: BTreeMap<&'static str, u64>,
+    pub blocks: BTreeMap<usize, Block>,
+    /// Taken jumps, counted per `(from, to)`.
+    pub jumps: BTreeMap<(usize, usize), u64>,
+    block: usize,
+    /// Call stacks as `(parent, function)`, interned; 0 is the bottom.
+    frames: Vec<(usize, usize)>,
+    frame_ids: HashMap<(usize, usize), usize>,
+    frame: usize,
+    /// Instructions executed per `(call stack, block)`.
+    folded: HashMap<(usize, usize), u64>,
+}
+
+impl Profile {
+    /// A profile of a process about to execute the instruction at `ip`.
+    pub fn new(ip: usize) -> Self {
+        let mut profile = Self {
+            block: ip,
+            frames: vec![(0, 0)],
+            ..Self::default()
+        };
+        profile.blocks.entry(ip).or_default().entries += 1;
+        profile
+    }
+
+    /// Counts `instruction` at `ip`, which left the process at `next`.
+    pub fn record(&mut self, ip: usize, instruction: Instruction, next: usize) {
+        self.total += 1;
+        *self.hits.entry(ip).or_default() += 1;
+        *self.opcodes.entry(instruction.mnemonic()).or_default() += 1;
+        let block = self.blocks.entry(self.block).or_default();
+        block.instructions += 1;
+        block.end = block.end.max(ip + instruction.size());
+        *self.folded.entry((self.frame, self.block)).or_default() += 1;
+        match instruction {
+            Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) => {
+                if next != ip + instruction.size() {
+                    *self.jumps.entry((ip, next)).or_default() += 1;
+                }
+                self.block = next;
+                self.blocks.entry(next).or_default().entries += 1;
+            }
+            Instruction::AdjustRelativeBase(n) if n.mode == ParameterMode::Immediate => {
+                if n.value > 0 {
+                    let key = (self.frame, self.block);
+                    let frames = &mut self.frames;
+                    self.frame = *self.frame_ids.entry(key).or_insert_with(|| {
+                        frames.push(key);
+                        frames.len() - 1
+                    });
+                } else if n.value < 0 {
+                    self.frame = self.frames[self.frame].0;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Taken jumps that go backwards or to themselves, i.e. loops, with
+    /// the most taken first.
+    pub fn back_edges(&self) -> Vec<((usize, usize), u64)> {
+        let mut edges: Vec<_> = self
+            .jumps
+            .iter()
+            .filter(|((from, to), _)| to <= from)
+            .map(|(&edge, &count)| (edge, count))
+            .collect();
+        edges.sort_by_key(|&(edge, count)| (std::cmp::Reverse(count), edge));
+        edges
+    }
+
+    /// Blocks by instructions executed, hottest first.
+    pub fn hot_blocks(&self) -> Vec<(usize, Block)> {
+        let mut blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .filter(|(_, block)| block.instructions > 0)
+            .map(|(&addr, &block)| (addr, block))
+            .collect();
+        blocks.sort_by_key(|&(addr, block)| (std::cmp::Reverse(block.instructions), addr));
+        blocks
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        100.0 * count as f64 / self.total.max(1) as f64
+    }
+
+    /// A summary of opcodes, hot blocks and back-edges, followed by the
+    /// disassembly of `code` with execution counts beside each line.
+    pub fn report(&self, code: &[i64]) -> String {
+        let mut out = format!("; {} instructions executed\n;\n; opcodes\n", self.total);
+        let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+        opcodes.sort_by_key(|&(mnemonic, &count)| (std::cmp::Reverse(count), *mnemonic));
+        for (mnemonic, &count) in opcodes {
+            out += &format!(
+                ";   {:<6}{:>12}  {:5.1}%\n",
+                mnemonic,
+                count,
+                self.percent(count)
+            );
+        }
+        out += ";\n; hot blocks\n";
+        for (addr, block) in self.hot_blocks().into_iter().take(TOP) {
+            let span = format!("{}..{}", addr, block.end);
+            out += &format!(
+                ";   {:<14}{:>12}  {:5.1}%  entered {}\n",
+                span,
+                block.instructions,
+                self.percent(block.instructions),
+                block.entries
+            );
+        }
+        out += ";\n; back-edges\n";
+        for ((from, to), count) in self.back_edges().into_iter().take(TOP) {
+            out += &format!(";   {:<14}{:>12}\n", format!("{} -> {}", from, to), count);
+        }
+        out += ";\n";
+        out += &disassemble(code).annotate(|entry| {
+            let count: u64 = self
+                .hits
+                .range(entry.addr..entry.addr + entry.len())
+                .map(|(_, &count)| count)
+                .sum();
+            if count == 0 {
+                String::new()
+            } else if entry.instruction.is_none() {
+                format!("{} (executed as patched code)", count)
+            } else {
+                format!("{:>10}  {:5.1}%", count, self.percent(count))
+            }
+        });
+        out
+    }
+
+    /// Writes the counts in the folded stack format flamegraph tools take:
+    /// one `main;function;...;block count` line per call stack and block.
+    pub fn write_folded<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut lines: Vec<_> = self
+            .folded
+            .iter()
+            .map(|(&(frame, block), &count)| {
+                let mut names = vec![label(block)];
+                let mut frame = frame;
+                while frame != 0 {
+                    let (parent, function) = self.frames[frame];
+                    names.push(label(function));
+                    frame = parent;
+                }
+                names.push("main".to_owned());
+                names.reverse();
+                (names.join(";"), count)
+            })
+            .collect();
+        lines.sort();
+        for (stack, count) in lines {
+            writeln!(writer, "{} {}", stack, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::asm::assemble;
+    use crate::intcode::{BufferIo, Process};
+
+    #[test]
+    fn test_profile() {
+        let source = "
+                    in [n]
+            loop:   arb #1
+                    add [n], #-1, [n]
+                    arb #-1
+                    jnz [n], loop
+                    halt
+            n:      .data 0
+        ";
+        let code = assemble(source).unwrap();
+        let mut process = Process::with_code(code.clone(), BufferIo::default());
+        process.input(3);
+        process.enable_profile();
+        process.execute().unwrap();
+        let profile = process.take_profile().unwrap();
+        assert_eq!(profile.total, 13);
+        assert_eq!(profile.hits[&2], 3);
+        assert_eq!(profile.opcodes["arb"], 6);
+        assert_eq!(profile.back_edges(), vec![((10, 2), 2)]);
+        let (addr, block) = profile.hot_blocks()[0];
+        assert_eq!((addr, block.entries, block.instructions), (2, 2, 8));
+
+        let mut folded = vec![];
+        profile.write_folded(&mut folded).unwrap();
+        assert_eq!(
+            String::from_utf8(folded).unwrap(),
+            "main;L0 3\nmain;L0;L0 2\nmain;L2 4\nmain;L2;L2 4\n"
+        );
+
+        let report = profile.report(&code);
+        assert!(report.contains("0002  109,1                   arb #1"));
+        assert!(report.contains(&format!(";   {:<14}{:>12}\n", "10 -> 2", 2)));
+    }
+}
@@ -0,0 +1,187 @@
+//! Trying many inputs on one program, spread over a pool of threads.
+//!
+//! ```text
+//! let best = Search::new(&program).best((0..=4).permutations(5), |program, phases| {
+//!     AmpChain::new(program, phases.clone()).output()
+//! })?;
+//! ```
+//!
+//! Results don't depend on the number of threads: ties and the first
+//! match are decided by the order of the candidates, and if runs fail, the
+//! error of the earliest failing candidate is returned.
+
+use super::{BufferIo, IntcodeError, Process};
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+pub struct Search<'a, IO = BufferIo> {
+    base: &'a Process<IO>,
+    threads: usize,
+}
+
+impl<'a, IO: Sync> Search<'a, IO> {
+    /// A search over `base` using one thread per available core.
+    pub fn new(base: &'a Process<IO>) -> Self {
+        Self {
+            base,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    pub fn threads(self, threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            ..self
+        }
+    }
+
+    /// Hands candidates to `work` along with their position, on all threads,
+    /// until they run out or reach `limit`.
+    fn scatter<I, C, W>(&self, candidates: I, limit: &AtomicUsize, work: W)
+    where
+        I: Iterator<Item = C> + Send,
+        C: Send,
+        W: Fn(usize, C) + Sync,
+    {
+        let candidates = Mutex::new(candidates.enumerate());
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                scope.spawn(|| loop {
+                    let next = candidates.lock().unwrap().next();
+                    match next {
+                        Some((index, candidate)) if index < limit.load(Ordering::SeqCst) => {
+                            work(index, candidate)
+                        }
+                        _ => break,
+                    }
+                });
+            }
+        });
+    }
+
+    /// Scores every candidate and returns the highest scoring one with its
+    /// score, the earliest of them on ties.
+    pub fn best<I, C, S, F>(&self, candidates: I, score: F) -> Result<Option<(C, S)>, IntcodeError>
+    where
+        I: IntoIterator<Item = C>,
+        I::IntoIter: Send,
+        C: Send,
+        S: Ord + Send,
+        F: Fn(&Process<IO>, &C) -> Result<S, IntcodeError> + Sync,
+    {
+        let limit = AtomicUsize::new(usize::MAX);
+        let best: Mutex<Option<(S, Reverse<usize>, C)>> = Mutex::new(None);
+        let error: Mutex<Option<(usize, IntcodeError)>> = Mutex::new(None);
+        self.scatter(
+            candidates.into_iter(),
+            &limit,
+            |index, candidate| match score(self.base, &candidate) {
+                Ok(score) => {
+                    let mut best = best.lock().unwrap();
+                    let better = best.as_ref().is_none_or(|(best_score, best_index, _)| {
+                        (&score, Reverse(index)) > (best_score, *best_index)
+                    });
+                    if better {
+                        *best = Some((score, Reverse(index), candidate));
+                    }
+                }
+                Err(err) => {
+                    let mut error = error.lock().unwrap();
+                    if error.as_ref().is_none_or(|&(first, _)| index < first) {
+                        *error = Some((index, err));
+                        limit.fetch_min(index, Ordering::SeqCst);
+                    }
+                }
+            },
+        );
+        if let Some((_, err)) = error.into_inner().unwrap() {
+            return Err(err);
+        }
+        Ok(best
+            .into_inner()
+            .unwrap()
+            .map(|(score, _, candidate)| (candidate, score)))
+    }
+
+    /// Returns the first candidate, in the order given, that satisfies
+    /// `predicate`. Later candidates may still be tried in parallel, but
+    /// only until an earlier match is known.
+    pub fn find<I, C, F>(&self, candidates: I, predicate: F) -> Result<Option<C>, IntcodeError>
+    where
+        I: IntoIterator<Item = C>,
+        I::IntoIter: Send,
+        C: Send,
+        F: Fn(&Process<IO>, &C) -> Result<bool, IntcodeError> + Sync,
+    {
+        let limit = AtomicUsize::new(usize::MAX);
+        let found: Mutex<Option<(usize, Result<C, IntcodeError>)>> = Mutex::new(None);
+        self.scatter(candidates.into_iter(), &limit, |index, candidate| {
+            let outcome = match predicate(self.base, &candidate) {
+                Ok(true) => Ok(candidate),
+                Ok(false) => return,
+                Err(err) => Err(err),
+            };
+            let mut found = found.lock().unwrap();
+            if found.as_ref().is_none_or(|&(first, _)| index < first) {
+                *found = Some((index, outcome));
+                limit.fetch_min(index, Ordering::SeqCst);
+            }
+        });
+        found
+            .into_inner()
+            .unwrap()
+            .map(|(_, outcome)| outcome)
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    /// Outputs its input modulo 7, failing for 20 and up.
+    fn program() -> Process {
+        let source = "
+                    in [n]
+                    lt [n], #20, [t]
+                    jz [t], fail
+            mod:    lt [n], #7, [t]
+                    jnz [t], done
+                    add [n], #-7, [n]
+                    jz #0, mod
+            done:   out [n]
+                    halt
+            fail:   .data 0
+            n:      .data 0
+            t:      .data 0
+        ";
+        Process::with_code(assemble(source).unwrap(), BufferIo::default())
+    }
+
+    fn run(program: &Process, input: &i64) -> Result<i64, IntcodeError> {
+        let mut process = program.folk();
+        process.input(*input);
+        process.execute()?;
+        Ok(process.output().unwrap())
+    }
+
+    #[test]
+    fn test_search() {
+        let program = program();
+        for &threads in &[1, 2, 8] {
+            let search = Search::new(&program).threads(threads);
+            // 6 and 13 both score 6; the earlier one wins.
+            assert_eq!(search.best(0..20, run), Ok(Some((6, 6))));
+            assert_eq!(search.find(0..20, |p, n| Ok(run(p, n)? == 3)), Ok(Some(3)));
+            assert_eq!(search.find(0..3, |p, n| Ok(run(p, n)? == 3)), Ok(None));
+            assert_eq!(
+                search.find(15..30, |p, n| Ok(run(p, n)? == 3)),
+                Ok(Some(17))
+            );
+            assert!(search.best(15..30, run).is_err());
+        }
+    }
+}
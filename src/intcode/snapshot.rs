@@ -0,0 +1,98 @@
+use super::{BufferIo, Process};
+use std::sync::Arc;
+
+/// The complete state of a `Process`: memory, registers and pending I/O.
+///
+/// Snapshots share memory with the process they were taken from and the
+/// processes restored from them, so taking and restoring one is cheap: the
+/// memory is only copied once one of them writes to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub code: Arc<Vec<i64>>,
+    pub ip: usize,
+    pub relative_base: i64,
+    /// Input queued but not yet consumed, oldest first.
+    pub input: Vec<i64>,
+    /// Output produced but not yet read, oldest first.
+    pub output: Vec<i64>,
+}
+
+impl Snapshot {
+    /// Creates a new process in this state.
+    pub fn to_process(&self) -> Process {
+        let mut process = Process::with_code(Vec::new(), BufferIo::default());
+        process.restore(self);
+        process
+    }
+}
+
+impl Process {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            code: Arc::clone(&self.code),
+            ip: self.ip,
+            relative_base: self.relative_base,
+            input: self.io.input.iter().copied().collect(),
+            output: self.io.output.iter().copied().collect(),
+        }
+    }
+
+    /// Puts the process back into the state of `snapshot`, replacing any
+    /// pending input and output. An active trace keeps recording.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.code = Arc::clone(&snapshot.code);
+        self.clear_decode_cache();
+        self.ip = snapshot.ip;
+        self.relative_base = snapshot.relative_base;
+        self.io.input = snapshot.input.iter().copied().collect();
+        self.io.output = snapshot.output.iter().copied().collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::Status;
+
+    #[test]
+    fn test_branch_keeps_pending_io() {
+        let mut process = Process::new("3,11,3,12,1,11,12,13,4,13,99,0,0,0");
+        process.input(1);
+        process.input(2);
+        assert_eq!(process.run(), Ok(Status::Output(3)));
+        process.input(7);
+        process.io.output.push_back(42);
+        let snapshot = process.snapshot();
+        assert_eq!(snapshot.input, vec![7]);
+        assert_eq!(snapshot.output, vec![42]);
+
+        let mut branch = process.folk();
+        assert_eq!(branch.output(), Some(42));
+        assert_eq!(branch.run(), Ok(Status::Halted));
+
+        process.restore(&Snapshot {
+            ip: 0,
+            input: vec![10, 20],
+            output: vec![],
+            ..snapshot
+        });
+        assert_eq!(process.run(), Ok(Status::Output(30)));
+        assert_eq!(process.output(), None);
+    }
+
+    #[test]
+    fn test_memory_shared_until_written() {
+        let mut process = Process::new("1101,2,3,5,99,0");
+        let snapshot = process.snapshot();
+        let mut branch = snapshot.to_process();
+        assert!(Arc::ptr_eq(&branch.code, &snapshot.code));
+        assert_eq!(branch.run(), Ok(Status::Halted));
+        assert_eq!(branch.read(5), 5);
+        assert_eq!(snapshot.code[5], 0);
+
+        assert_eq!(process.run(), Ok(Status::Halted));
+        process.restore(&snapshot);
+        assert!(Arc::ptr_eq(&process.code, &snapshot.code));
+        assert_eq!(process.read(5), 0);
+    }
+}
@@ -0,0 +1,421 @@
+//! Runs a program with some memory cells left as unknowns, producing
+//! polynomials in those unknowns instead of numbers.
+//!
+//! Only programs whose control flow and addressing don't depend on the
+//! unknowns can be evaluated this way. Day 2 is the typical case: noun and
+//! verb are only ever added and multiplied.
+
+use super::{Instruction, IntcodeError, ParameterMode, MAX_ADDRESS};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Gives up on programs running longer than this.
+const MAX_STEPS: usize = 1_000_000;
+
+/// A polynomial with integer coefficients in the variables `x0`, `x1`, ...
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Poly {
+    /// Coefficients by the exponent of each variable; never zero.
+    terms: BTreeMap<Vec<u32>, i64>,
+}
+
+impl Poly {
+    pub fn constant(value: i64) -> Self {
+        let mut poly = Self::default();
+        if value != 0 {
+            poly.terms.insert(vec![], value);
+        }
+        poly
+    }
+
+    pub fn var(n: usize) -> Self {
+        let mut exponents = vec![0; n + 1];
+        exponents[n] = 1;
+        Self {
+            terms: vec![(exponents, 1)].into_iter().collect(),
+        }
+    }
+
+    /// The value if there are no variables left.
+    pub fn as_constant(&self) -> Option<i64> {
+        match self.terms.len() {
+            0 => Some(0),
+            1 => self.terms.get(&vec![]).copied(),
+            _ => None,
+        }
+    }
+
+    pub fn degree(&self) -> u32 {
+        self.terms
+            .keys()
+            .map(|exponents| exponents.iter().sum())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The coefficient of the term with these exponents.
+    pub fn coefficient(&self, exponents: &[u32]) -> i64 {
+        let len = exponents.iter().rposition(|&e| e != 0).map_or(0, |i| i + 1);
+        self.terms.get(&exponents[..len]).copied().unwrap_or(0)
+    }
+
+    fn insert(&mut self, exponents: Vec<u32>, coefficient: i64) -> Option<()> {
+        let len = exponents.iter().rposition(|&e| e != 0).map_or(0, |i| i + 1);
+        let mut exponents = exponents;
+        exponents.truncate(len);
+        let sum = self
+            .terms
+            .get(&exponents)
+            .copied()
+            .unwrap_or(0)
+            .checked_add(coefficient)?;
+        if sum == 0 {
+            self.terms.remove(&exponents);
+        } else {
+            self.terms.insert(exponents, sum);
+        }
+        Some(())
+    }
+
+    /// `None` on overflow.
+    pub fn checked_add(&self, other: &Poly) -> Option<Poly> {
+        let mut sum = self.clone();
+        for (exponents, &coefficient) in &other.terms {
+            sum.insert(exponents.clone(), coefficient)?;
+        }
+        Some(sum)
+    }
+
+    /// `None` on overflow.
+    pub fn checked_mul(&self, other: &Poly) -> Option<Poly> {
+        let mut product = Poly::default();
+        for (a, &x) in &self.terms {
+            for (b, &y) in &other.terms {
+                let len = a.len().max(b.len());
+                let exponents = (0..len)
+                    .map(|i| a.get(i).unwrap_or(&0) + b.get(i).unwrap_or(&0))
+                    .collect();
+                product.insert(exponents, x.checked_mul(y)?)?;
+            }
+        }
+        Some(product)
+    }
+
+    /// The value at `vars`, or `None` on overflow.
+    pub fn eval(&self, vars: &[i64]) -> Option<i64> {
+        self.terms
+            .iter()
+            .try_fold(0i64, |sum, (exponents, &coefficient)| {
+                let term = exponents
+                    .iter()
+                    .enumerate()
+                    .try_fold(coefficient, |term, (i, &e)| {
+                        term.checked_mul(vars[i].checked_pow(e)?)
+                    })?;
+                sum.checked_add(term)
+            })
+    }
+}
+
+impl fmt::Display for Poly {
+    /// Formats as e.g. `360000*x0 + x1 + 250635`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        for (n, (exponents, &coefficient)) in self.terms.iter().rev().enumerate() {
+            let vars: Vec<String> = exponents
+                .iter()
+                .enumerate()
+                .filter(|&(_, &e)| e > 0)
+                .map(|(i, &e)| match e {
+                    1 => format!("x{}", i),
+                    _ => format!("x{}^{}", i, e),
+                })
+                .collect();
+            let sign = if coefficient < 0 { "-" } else { "+" };
+            if n == 0 {
+                if coefficient < 0 {
+                    write!(f, "-")?;
+                }
+            } else {
+                write!(f, " {} ", sign)?;
+            }
+            let magnitude = coefficient.unsigned_abs();
+            if vars.is_empty() {
+                write!(f, "{}", magnitude)?;
+            } else if magnitude == 1 {
+                write!(f, "{}", vars.join("*"))?;
+            } else {
+                write!(f, "{}*{}", magnitude, vars.join("*"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why a program couldn't be evaluated symbolically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolicError {
+    /// What the program does at `ip` depends on the unknowns.
+    Unknown {
+        ip: usize,
+    },
+    /// The program does I/O at `ip`.
+    Io {
+        ip: usize,
+    },
+    Overflow {
+        ip: usize,
+    },
+    StepLimit,
+    Intcode(IntcodeError),
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolicError::Unknown { ip } => write!(f, "at ip {}: depends on the unknowns", ip),
+            SymbolicError::Io { ip } => write!(f, "at ip {}: I/O can't be evaluated", ip),
+            SymbolicError::Overflow { ip } => write!(f, "at ip {}: coefficient overflow", ip),
+            SymbolicError::StepLimit => write!(f, "gave up after {} steps", MAX_STEPS),
+            SymbolicError::Intcode(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+impl From<IntcodeError> for SymbolicError {
+    fn from(error: IntcodeError) -> Self {
+        SymbolicError::Intcode(error)
+    }
+}
+
+/// A memory cell: a polynomial, or `None` for a value that depends on the
+/// unknowns in a way a polynomial can't express, like a read through an
+/// unknown address. Such values are fine as long as nothing uses them.
+type Cell = Option<Poly>;
+
+struct Evaluator {
+    memory: Vec<Cell>,
+    ip: usize,
+    relative_base: i64,
+    /// The instruction at `ip`, decoded from its opcode alone, so operand
+    /// values are placeholders; `operands` holds the actual ones.
+    shape: Instruction,
+    opcode: i64,
+    operands: Vec<Cell>,
+}
+
+impl Evaluator {
+    fn word(&self, addr: usize) -> Cell {
+        match self.memory.get(addr) {
+            Some(cell) => cell.clone(),
+            None => Some(Poly::default()),
+        }
+    }
+
+    fn unknown(&self) -> SymbolicError {
+        SymbolicError::Unknown { ip: self.ip }
+    }
+
+    fn constant(&self, cell: Cell) -> Result<i64, SymbolicError> {
+        cell.and_then(|poly| poly.as_constant())
+            .ok_or_else(|| self.unknown())
+    }
+
+    fn address(&self, addr: i64) -> Result<usize, SymbolicError> {
+        if (0..MAX_ADDRESS).contains(&addr) {
+            Ok(addr as usize)
+        } else {
+            Err(IntcodeError::InvalidAddress {
+                ip: self.ip,
+                opcode: self.opcode,
+                addr,
+            }
+            .into())
+        }
+    }
+
+    /// The address operand `i` refers to, `None` in immediate mode.
+    fn param_addr(&self, i: usize) -> Result<Option<usize>, SymbolicError> {
+        let value = match self.shape.params()[i].mode {
+            ParameterMode::Immediate => return Ok(None),
+            ParameterMode::Position => self.constant(self.operands[i].clone())?,
+            ParameterMode::Relative => self
+                .relative_base
+                .saturating_add(self.constant(self.operands[i].clone())?),
+        };
+        self.address(value).map(Some)
+    }
+
+    fn load(&self, i: usize) -> Result<Cell, SymbolicError> {
+        match self.param_addr(i) {
+            Ok(Some(addr)) => Ok(self.word(addr)),
+            Ok(None) => Ok(self.operands[i].clone()),
+            // A read through an unknown address is itself unknown.
+            Err(SymbolicError::Unknown { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn store(&mut self, i: usize, value: Cell) -> Result<(), SymbolicError> {
+        let addr = self.param_addr(i)?.unwrap();
+        if addr >= self.memory.len() {
+            self.memory.resize(addr + 1, Some(Poly::default()));
+        }
+        self.memory[addr] = value;
+        Ok(())
+    }
+
+    /// Executes one instruction, returning whether the program halted.
+    fn step(&mut self) -> Result<bool, SymbolicError> {
+        let ip = self.ip;
+        self.opcode = self.constant(self.word(ip))?;
+        self.shape = Instruction::decode(&[self.opcode], 0).map_err(|error| match error {
+            IntcodeError::UnknownOpcode { opcode, .. } => {
+                IntcodeError::UnknownOpcode { ip, opcode }
+            }
+            IntcodeError::UnknownMode { opcode, mode, .. } => {
+                IntcodeError::UnknownMode { ip, opcode, mode }
+            }
+            IntcodeError::ImmediateWrite { opcode, .. } => {
+                IntcodeError::ImmediateWrite { ip, opcode }
+            }
+            error => error,
+        })?;
+        self.operands = (1..self.shape.size()).map(|i| self.word(ip + i)).collect();
+        let mut next = ip + self.shape.size();
+        match self.shape {
+            Instruction::Add(..) | Instruction::Mul(..) => {
+                let value = match (self.load(0)?, self.load(1)?) {
+                    (Some(a), Some(b)) => {
+                        let value = if let Instruction::Add(..) = self.shape {
+                            a.checked_add(&b)
+                        } else {
+                            a.checked_mul(&b)
+                        };
+                        Some(value.ok_or(SymbolicError::Overflow { ip })?)
+                    }
+                    _ => None,
+                };
+                self.store(2, value)?;
+            }
+            Instruction::LessThan(..) | Instruction::Equals(..) => {
+                let a = self.constant(self.load(0)?)?;
+                let b = self.constant(self.load(1)?)?;
+                let holds = if let Instruction::LessThan(..) = self.shape {
+                    a < b
+                } else {
+                    a == b
+                };
+                self.store(2, Some(Poly::constant(holds as i64)))?;
+            }
+            Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) => {
+                let cond = self.constant(self.load(0)?)?;
+                if (cond != 0) == matches!(self.shape, Instruction::JumpIfTrue(..)) {
+                    next = self.address(self.constant(self.load(1)?)?)?;
+                }
+            }
+            Instruction::AdjustRelativeBase(..) => {
+                let base = self
+                    .relative_base
+                    .checked_add(self.constant(self.load(0)?)?);
+                self.relative_base = base.ok_or(SymbolicError::Overflow { ip })?;
+            }
+            Instruction::In(..) | Instruction::Out(..) => return Err(SymbolicError::Io { ip }),
+            Instruction::Halt => return Ok(true),
+        }
+        self.ip = next;
+        Ok(false)
+    }
+}
+
+/// Runs `code` until it halts, with the cells at `vars` replaced by the
+/// variables `x0`, `x1`, ... Returns the final memory.
+pub fn evaluate(code: &[i64], vars: &[usize]) -> Result<Vec<Cell>, SymbolicError> {
+    let mut memory: Vec<Cell> = code.iter().map(|&n| Some(Poly::constant(n))).collect();
+    for (n, &addr) in vars.iter().enumerate() {
+        if addr >= memory.len() {
+            memory.resize(addr + 1, Some(Poly::default()));
+        }
+        memory[addr] = Some(Poly::var(n));
+    }
+    let mut evaluator = Evaluator {
+        memory,
+        ip: 0,
+        relative_base: 0,
+        shape: Instruction::Halt,
+        opcode: 0,
+        operands: vec![],
+    };
+    for _ in 0..MAX_STEPS {
+        if evaluator.step()? {
+            return Ok(evaluator.memory);
+        }
+    }
+    Err(SymbolicError::StepLimit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poly() {
+        let x = Poly::var(0);
+        let y = Poly::var(1);
+        let p = x
+            .checked_mul(&Poly::constant(3))
+            .unwrap()
+            .checked_add(&y.checked_mul(&y).unwrap())
+            .unwrap()
+            .checked_add(&Poly::constant(-4))
+            .unwrap();
+        assert_eq!(p.to_string(), "3*x0 + x1^2 - 4");
+        assert_eq!(p.eval(&[2, 5]), Some(27));
+        assert_eq!(p.degree(), 2);
+        assert_eq!(p.coefficient(&[1, 0]), 3);
+        assert_eq!(
+            p.checked_add(&Poly::constant(4)).unwrap().as_constant(),
+            None
+        );
+        assert_eq!(
+            x.checked_add(&Poly::constant(-1))
+                .unwrap()
+                .checked_add(&Poly::constant(1))
+                .unwrap(),
+            x
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        // Like day 2: reads through the unknown addresses first, then
+        // computes code[0] = (x0 + x1) * code[17] + code[18].
+        let code = [1, 0, 0, 19, 1, 1, 2, 3, 2, 3, 17, 0, 1, 0, 18, 0, 99, 5, 7];
+        let memory = evaluate(&code, &[1, 2]).unwrap();
+        assert_eq!(memory[0].as_ref().unwrap().to_string(), "5*x0 + 5*x1 + 7");
+        assert_eq!(memory[19], None);
+
+        // Jumping on an unknown.
+        assert_eq!(
+            evaluate(&[1005, 1, 0, 99], &[1]),
+            Err(SymbolicError::Unknown { ip: 0 })
+        );
+        assert_eq!(
+            evaluate(&[109, i64::MAX, 109, 1, 99], &[]),
+            Err(SymbolicError::Overflow { ip: 2 })
+        );
+        assert_eq!(
+            evaluate(&[109, i64::MAX, 21101, 1, 1, 1, 99], &[]),
+            Err(SymbolicError::Intcode(IntcodeError::InvalidAddress {
+                ip: 2,
+                opcode: 21101,
+                addr: i64::MAX
+            }))
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Running processes as futures, cooperatively on one thread.
+//!
+//! `Process::into_async` splits a process into a `Machine` future, an
+//! `InputSink` to feed it and an `OutputStream` of what it prints. The
+//! `LocalExecutor` drives any number of such futures without a runtime.
+
+use super::io::Closed;
+use super::{BufferIo, IntcodeError, IntcodeIo, Process, Status};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+/// A minimal asynchronous iterator.
+pub trait Stream {
+    type Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>>;
+
+    fn next(&mut self) -> Next<'_, Self>
+    where
+        Self: Unpin,
+    {
+        Next { stream: self }
+    }
+}
+
+pub struct Next<'a, S: ?Sized> {
+    stream: &'a mut S,
+}
+
+impl<S: Stream + Unpin + ?Sized> Future for Next<'_, S> {
+    type Output = Option<S::Item>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.stream).poll_next(cx)
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    values: VecDeque<i64>,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+impl Queue {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+type SharedQueue = Rc<RefCell<Queue>>;
+
+pub struct AsyncIo {
+    input: SharedQueue,
+    output: SharedQueue,
+}
+
+impl IntcodeIo for AsyncIo {
+    fn read_input(&mut self) -> Option<i64> {
+        self.input.borrow_mut().values.pop_front()
+    }
+
+    fn write_output(&mut self, value: i64) -> Result<(), Closed> {
+        let mut output = self.output.borrow_mut();
+        output.values.push_back(value);
+        output.wake();
+        Ok(())
+    }
+}
+
+/// A process as a future resolving to its final memory.
+pub struct Machine {
+    process: Process<AsyncIo>,
+}
+
+impl Machine {
+    fn finish(&mut self) {
+        let mut output = self.process.io.output.borrow_mut();
+        output.closed = true;
+        output.wake();
+    }
+}
+
+impl Future for Machine {
+    type Output = Result<Vec<i64>, IntcodeError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = match self.process.run() {
+            Ok(Status::Output(value)) => {
+                self.process.io.write_output(value).unwrap();
+                // Let consumers run before producing more.
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Ok(Status::AwaitingInput) => {
+                let mut input = self.process.io.input.borrow_mut();
+                if !input.closed {
+                    input.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+                drop(input);
+                let ip = self.process.ip;
+                Err(IntcodeError::NoInput {
+                    ip,
+                    opcode: self.process.read(ip),
+                })
+            }
+            Ok(Status::Halted) => Ok(Arc::unwrap_or_clone(std::mem::take(&mut self.process.code))),
+            Err(err) => Err(err),
+        };
+        self.finish();
+        Poll::Ready(result)
+    }
+}
+
+/// Feeds input to a `Machine`. Dropping it tells the machine no more input
+/// is coming.
+pub struct InputSink(SharedQueue);
+
+impl InputSink {
+    pub fn send(&self, value: i64) {
+        let mut input = self.0.borrow_mut();
+        input.values.push_back(value);
+        input.wake();
+    }
+}
+
+impl Drop for InputSink {
+    fn drop(&mut self) {
+        let mut input = self.0.borrow_mut();
+        input.closed = true;
+        input.wake();
+    }
+}
+
+/// The output of a `Machine`, ending when it stops.
+pub struct OutputStream(SharedQueue);
+
+impl Stream for OutputStream {
+    type Item = i64;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<i64>> {
+        let mut output = self.0.borrow_mut();
+        if let Some(value) = output.values.pop_front() {
+            Poll::Ready(Some(value))
+        } else if output.closed {
+            Poll::Ready(None)
+        } else {
+            output.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Process {
+    /// Splits the process into a future and its I/O ends. Input and output
+    /// already queued are carried over.
+    pub fn into_async(mut self) -> (Machine, InputSink, OutputStream) {
+        let BufferIo { input, output } = std::mem::take(&mut self.io);
+        let input = Rc::new(RefCell::new(Queue {
+            values: input,
+            ..Queue::default()
+        }));
+        let output = Rc::new(RefCell::new(Queue {
+            values: output,
+            ..Queue::default()
+        }));
+        let io = AsyncIo {
+            input: input.clone(),
+            output: output.clone(),
+        };
+        let machine = Machine {
+            process: self.with_io(io),
+        };
+        (machine, InputSink(input), OutputStream(output))
+    }
+}
+
+struct TaskWaker {
+    id: usize,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.lock().unwrap().push_back(self.id);
+    }
+}
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Runs futures to completion on the current thread.
+#[derive(Default)]
+pub struct LocalExecutor {
+    tasks: Vec<Option<Task>>,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl LocalExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn<F: Future<Output = ()> + 'static>(&mut self, future: F) {
+        self.ready.lock().unwrap().push_back(self.tasks.len());
+        self.tasks.push(Some(Box::pin(future)));
+    }
+
+    /// Polls tasks until none can make progress, returning how many are
+    /// still unfinished (and so waiting on something that never came).
+    pub fn run(&mut self) -> usize {
+        loop {
+            let id = match self.ready.lock().unwrap().pop_front() {
+                Some(id) => id,
+                None => break,
+            };
+            if let Some(task) = &mut self.tasks[id] {
+                let waker = Waker::from(Arc::new(TaskWaker {
+                    id,
+                    ready: self.ready.clone(),
+                }));
+                if task
+                    .as_mut()
+                    .poll(&mut Context::from_waker(&waker))
+                    .is_ready()
+                {
+                    self.tasks[id] = None;
+                }
+            }
+        }
+        self.tasks.iter().filter(|task| task.is_some()).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_machines_on_one_thread() {
+        let doubler = "3,9,1002,9,2,9,4,9,1105,1,0";
+        let (first, first_in, mut first_out) = Process::new(doubler).into_async();
+        let (second, second_in, mut second_out) = Process::new(doubler).into_async();
+        let results = Rc::new(RefCell::new(vec![]));
+        let mut executor = LocalExecutor::new();
+        executor.spawn(async move {
+            assert_eq!(first.await, Err(IntcodeError::NoInput { ip: 0, opcode: 3 }));
+        });
+        executor.spawn(async move {
+            let _ = second.await;
+        });
+        executor.spawn(async move {
+            while let Some(value) = first_out.next().await {
+                second_in.send(value);
+            }
+        });
+        let sink = results.clone();
+        executor.spawn(async move {
+            while let Some(value) = second_out.next().await {
+                sink.borrow_mut().push(value);
+            }
+        });
+        first_in.send(1);
+        first_in.send(3);
+        assert_eq!(executor.run(), 4);
+        assert_eq!(*results.borrow(), vec![4, 12]);
+        drop(first_in);
+        assert_eq!(executor.run(), 0);
+    }
+}
@@ -0,0 +1,125 @@
+use super::io::{ChannelIo, Closed, IntcodeIo};
+use super::{IntcodeError, Process};
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// A process running on its own thread, see `Process::spawn_with`.
+pub struct Running {
+    thread: JoinHandle<Result<Vec<i64>, IntcodeError>>,
+}
+
+impl Running {
+    /// Waits for the program to halt and returns its final memory.
+    ///
+    /// A program still waiting for input when every sender has been dropped
+    /// fails with `IntcodeError::NoInput`.
+    pub fn join(self) -> Result<Vec<i64>, IntcodeError> {
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+/// A process running on its own thread along with its channels, see
+/// `Process::spawn`.
+pub struct Handle {
+    pub input: Sender<i64>,
+    pub output: Receiver<i64>,
+    running: Running,
+}
+
+impl Handle {
+    /// Drops `input` and waits for the program to halt, see `Running::join`.
+    pub fn join(self) -> Result<Vec<i64>, IntcodeError> {
+        drop(self.input);
+        self.running.join()
+    }
+}
+
+/// Input still queued in the process when it was spawned, then the channels.
+struct Spawned {
+    queued: VecDeque<i64>,
+    channels: ChannelIo,
+}
+
+impl IntcodeIo for Spawned {
+    fn read_input(&mut self) -> Option<i64> {
+        self.queued
+            .pop_front()
+            .or_else(|| self.channels.read_input())
+    }
+
+    fn write_output(&mut self, value: i64) -> Result<(), Closed> {
+        self.channels.write_output(value)
+    }
+}
+
+impl Process {
+    /// Moves the process onto a new thread where input instructions block
+    /// until a value is sent. Input and output already queued are carried over.
+    pub fn spawn(self) -> Handle {
+        let (input, input_rx) = channel();
+        let (output_tx, output) = channel();
+        Handle {
+            input,
+            output,
+            running: self.spawn_with(input_rx, output_tx),
+        }
+    }
+
+    /// Like `spawn`, but reading from `input` and sending to `output`, so the
+    /// output of one spawned process can feed the input of the next.
+    pub fn spawn_with(mut self, input: Receiver<i64>, output: Sender<i64>) -> Running {
+        for value in self.io.output.drain(..) {
+            // Nobody listening is reported once the program outputs again.
+            let _ = output.send(value);
+        }
+        let io = Spawned {
+            queued: std::mem::take(&mut self.io.input),
+            channels: ChannelIo {
+                input,
+                output,
+                blocking: true,
+            },
+        };
+        let mut process = self.with_io(io);
+        let thread = thread::spawn(move || {
+            process.execute()?;
+            Ok(Arc::unwrap_or_clone(process.code))
+        });
+        Running { thread }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawned_pipeline() {
+        let doubler = "3,9,1002,9,2,9,4,9,1105,1,0";
+        let (input, first_rx) = channel();
+        let (first_tx, second_rx) = channel();
+        let (second_tx, output) = channel();
+        let mut first = Process::new(doubler);
+        first.input(3);
+        let first = first.spawn_with(first_rx, first_tx);
+        let second = Process::new(doubler).spawn_with(second_rx, second_tx);
+        input.send(5).unwrap();
+        assert_eq!(output.recv(), Ok(12));
+        assert_eq!(output.recv(), Ok(20));
+        drop(input);
+        let waiting = Err(IntcodeError::NoInput { ip: 0, opcode: 3 });
+        assert_eq!(first.join(), waiting);
+        assert_eq!(second.join(), waiting);
+
+        let mut halting = Process::new("3,0,4,0,99");
+        halting.input(7);
+        let handle = halting.spawn();
+        assert_eq!(handle.output.recv(), Ok(7));
+        assert_eq!(handle.join(), Ok(vec![7, 0, 4, 0, 99]));
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryWrite {
+    pub addr: usize,
+    pub old: i64,
+    pub new: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoEvent {
+    Input(i64),
+    Output(i64),
+}
+
+/// One executed instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub ip: usize,
+    pub opcode: i64,
+    /// Operand values after applying parameter modes; write operands are
+    /// given as the address written to. The target of a jump not taken is
+    /// never read and so `None`.
+    pub operands: Vec<Option<i64>>,
+    pub write: Option<MemoryWrite>,
+    pub io: Option<IoEvent>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+}
+
+impl TraceEntry {
+    pub fn to_json(&self) -> String {
+        let operands: Vec<String> = self
+            .operands
+            .iter()
+            .map(|n| n.map_or("null".to_owned(), |n| n.to_string()))
+            .collect();
+        let mut json = format!(
+            r#"{{"ip":{},"opcode":{},"operands":[{}]"#,
+            self.ip,
+            self.opcode,
+            operands.join(",")
+        );
+        if let Some(write) = self.write {
+            json += &format!(
+                r#","write":{{"addr":{},"old":{},"new":{}}}"#,
+                write.addr, write.old, write.new
+            );
+        }
+        match self.io {
+            Some(IoEvent::Input(value)) => json += &format!(r#","input":{}"#, value),
+            Some(IoEvent::Output(value)) => json += &format!(r#","output":{}"#, value),
+            None => (),
+        }
+        json.push('}');
+        json
+    }
+}
+
+impl Trace {
+    /// Writes the trace as JSON Lines, one executed instruction per line.
+    pub fn write_jsonl<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for entry in &self.entries {
+            writeln!(writer, "{}", entry.to_json())?;
+        }
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@
 extern crate aoc_runner_derive;
 
 pub mod day2;
+pub mod day23;
 pub mod day3;
 pub mod day4;
 pub mod day5;